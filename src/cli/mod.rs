@@ -0,0 +1,291 @@
+use std::{fs, io::{stdin, Read}};
+
+use crate::{error, errors::{CliError, DynamicError, LexerError, ParserError, ParserErrors}};
+
+pub const STDIN_ARG: &str = "-";
+pub const ERROR_FORMAT_OPTION: &str = "--error-format";
+pub const GC_THRESHOLD_OPTION: &str = "--gc-threshold";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    File(String),
+    Stdin,
+    Inline(String),
+}
+
+impl Input {
+    /// Name used to refer to the input in messages
+    pub fn name(&self) -> String {
+        match self {
+            Input::File(path) => pretty_path(path),
+            Input::Stdin => String::from("<stdin>"),
+            Input::Inline(_) => String::from("<inline>"),
+        }
+    }
+
+    pub fn read(&self) -> Result<String, DynamicError> {
+        Ok(match self {
+            Input::File(path) => fs::read_to_string(path)
+                .map_err(|err| std::io::Error::new(err.kind(), format!("Could not read '{}': {}", self.name(), err)))?,
+            Input::Stdin => {
+                let mut content = String::new();
+                stdin().read_to_string(&mut content)
+                    .map_err(|err| std::io::Error::new(err.kind(), format!("Could not read {}: {}", self.name(), err)))?;
+                content
+            },
+            Input::Inline(code) => code.to_owned(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Run { input: Input, args: Vec<String> },
+    Repl,
+    Check(Input),
+    Fmt(Input),
+    Tokens(Input),
+    Ast(Input),
+    Doc(Input),
+    Version,
+    Help,
+}
+
+/// How errors are reported on standard error
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "human" => Some(ErrorFormat::Human),
+            "json" => Some(ErrorFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Finds the format among the leading options, for errors raised before they are parsed
+    pub fn from_args(args: &[String]) -> Self {
+        let mut args = args.iter().take_while(|arg| arg.starts_with('-') && *arg != STDIN_ARG);
+
+        while let Some(arg) = args.next() {
+            let value = match arg.strip_prefix(ERROR_FORMAT_OPTION) {
+                Some("") => args.next().map(|value| value.as_str()),
+                Some(value) => value.strip_prefix('='),
+                None => continue,
+            };
+
+            if let Some(format) = value.and_then(Self::from_name) {
+                return format;
+            }
+        }
+
+        Self::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cli {
+    pub command: Command,
+    pub error_format: ErrorFormat,
+    /// Bytes allocated between garbage collections, the collector's default when not given
+    pub gc_threshold: Option<usize>,
+}
+
+impl Cli {
+    /// Parses the command line arguments, excluding the program name
+    pub fn parse(args: &[String]) -> Result<Self, DynamicError> {
+        let mut args = args.iter();
+        let mut inline: Option<String> = None;
+        let mut error_format = ErrorFormat::default();
+        let mut gc_threshold = None;
+
+        // Leading options
+        let mut first = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(Self { command: Command::Help, error_format, gc_threshold }),
+                "-V" | "--version" => return Ok(Self { command: Command::Version, error_format, gc_threshold }),
+                "-e" | "--eval" => match args.next() {
+                    Some(code) => inline = Some(code.to_owned()),
+                    None => error!(CliError::MissingArgument { option: arg.to_owned() })
+                },
+                _ if Self::is_option(arg, ERROR_FORMAT_OPTION) => {
+                    let value = Self::option_value(arg, &mut args)?;
+
+                    error_format = match ErrorFormat::from_name(&value) {
+                        Some(format) => format,
+                        None => error!(CliError::InvalidValue { option: ERROR_FORMAT_OPTION.to_owned(), value })
+                    };
+                },
+                _ if Self::is_option(arg, GC_THRESHOLD_OPTION) => {
+                    let value = Self::option_value(arg, &mut args)?;
+
+                    gc_threshold = match value.parse::<usize>() {
+                        Ok(threshold) => Some(threshold),
+                        Err(_) => error!(CliError::InvalidValue { option: GC_THRESHOLD_OPTION.to_owned(), value })
+                    };
+                },
+                _ if arg.starts_with('-') && arg != STDIN_ARG => error!(CliError::UnknownOption { option: arg.to_owned() }),
+                _ => {
+                    first = Some(arg.to_owned());
+                    break;
+                }
+            }
+        }
+
+        let rest: Vec<String> = args.cloned().collect();
+
+        if let Some(code) = inline {
+            let mut args = rest;
+            if let Some(first) = first {
+                args.insert(0, first);
+            }
+
+            return Ok(Self { command: Command::Run { input: Input::Inline(code), args }, error_format, gc_threshold });
+        }
+
+        let first = match first {
+            Some(first) => first,
+            None => return Ok(Self { command: Command::Repl, error_format, gc_threshold }),
+        };
+
+        let command = match first.as_str() {
+            "run" => {
+                let (input, args) = Self::parse_input(&first, &rest)?;
+                Command::Run { input, args }
+            },
+            "repl" => Command::Repl,
+            "check" => Command::Check(Self::parse_single_input(&first, &rest)?),
+            "fmt" => Command::Fmt(Self::parse_single_input(&first, &rest)?),
+            "tokens" => Command::Tokens(Self::parse_single_input(&first, &rest)?),
+            "ast" => Command::Ast(Self::parse_single_input(&first, &rest)?),
+            "doc" => Command::Doc(Self::parse_single_input(&first, &rest)?),
+            "help" => Command::Help,
+            "version" => Command::Version,
+
+            // Running a file directly, e.g. `yaipl file.yaipl`
+            _ => Command::Run { input: Self::input_from(&first), args: rest },
+        };
+
+        Ok(Self { command, error_format, gc_threshold })
+    }
+
+    /// Whether the argument is the option, either on its own or as `option=value`
+    fn is_option(arg: &str, option: &str) -> bool {
+        arg == option || arg.strip_prefix(option).is_some_and(|rest| rest.starts_with('='))
+    }
+
+    /// Value of an option, given after '=' or as the next argument
+    fn option_value<'a>(arg: &str, args: &mut impl Iterator<Item = &'a String>) -> Result<String, DynamicError> {
+        match arg.split_once('=') {
+            Some((_, value)) => Ok(value.to_owned()),
+            None => match args.next() {
+                Some(value) => Ok(value.to_owned()),
+                None => error!(CliError::MissingArgument { option: arg.to_owned() })
+            }
+        }
+    }
+
+    fn input_from(arg: &str) -> Input {
+        match arg {
+            STDIN_ARG => Input::Stdin,
+            _ => Input::File(arg.to_owned()),
+        }
+    }
+
+    fn parse_input(command: &str, args: &[String]) -> Result<(Input, Vec<String>), DynamicError> {
+        match args {
+            [option, code, rest @ ..] if option == "-e" || option == "--eval" => Ok((Input::Inline(code.to_owned()), rest.to_vec())),
+            [option] if option == "-e" || option == "--eval" => error!(CliError::MissingArgument { option: option.to_owned() }),
+            [input, rest @ ..] => Ok((Self::input_from(input), rest.to_vec())),
+            [] => error!(CliError::MissingInput { command: command.to_owned() }),
+        }
+    }
+
+    fn parse_single_input(command: &str, args: &[String]) -> Result<Input, DynamicError> {
+        let (input, rest) = Self::parse_input(command, args)?;
+
+        if let Some(arg) = rest.first() {
+            error!(CliError::UnexpectedArgument { argument: arg.to_owned() })
+        }
+
+        Ok(input)
+    }
+
+    pub fn usage(name: &str) -> String {
+        [
+            format!("Usage: {} [options] [command] [file] [args...]", name),
+            String::new(),
+            String::from("Commands:"),
+            String::from("    run <file> [args...]  Run a script, passing the remaining arguments to it"),
+            String::from("    repl                  Start an interactive session (default)"),
+            String::from("    check <file>          Lex and parse a script without running it"),
+            String::from("    fmt <file>            Print a script in its canonical formatting"),
+            String::from("    tokens <file>         Print the tokens of a script"),
+            String::from("    ast <file>            Print the syntax tree of a script"),
+            String::from("    doc <file>            Print the documentation of a script's functions as Markdown"),
+            String::new(),
+            String::from("Options:"),
+            String::from("    -e, --eval <code>     Use the given code instead of a file"),
+            String::from("    --error-format <fmt>  Report errors as 'human' readable text or 'json' lines"),
+            String::from("    --gc-threshold <n>    Collect garbage every time n bytes have been allocated"),
+            String::from("    -V, --version         Print version information"),
+            String::from("    -h, --help            Print this message"),
+            String::new(),
+            format!("A file of '{}' reads the script from standard input.", STDIN_ARG),
+        ].join("\n")
+    }
+}
+
+/// Process exit codes, one per kind of error
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitStatus {
+    Success = 0,
+    RuntimeError = 1,
+    UsageError = 2,
+    IoError = 3,
+    LexerError = 4,
+    ParserError = 5,
+}
+
+impl ExitStatus {
+    pub fn from_error(err: &DynamicError) -> Self {
+        if err.is::<LexerError>() {
+            ExitStatus::LexerError
+        } else if err.is::<ParserError>() || err.is::<ParserErrors>() {
+            ExitStatus::ParserError
+        } else if err.is::<CliError>() {
+            ExitStatus::UsageError
+        } else if err.is::<std::io::Error>() {
+            ExitStatus::IoError
+        } else {
+            // Evaluator errors as well as type errors raised by objects
+            ExitStatus::RuntimeError
+        }
+    }
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// Shortens a path to be relative to the current directory when possible
+pub fn pretty_path(path: &str) -> String {
+    let absolute_path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => return path.to_owned(),
+    };
+
+    match std::env::current_dir() {
+        Ok(cwd) => match absolute_path.strip_prefix(&cwd) {
+            Ok(path) => path.display().to_string(),
+            Err(_) => absolute_path.display().to_string()
+        },
+        Err(_) => absolute_path.display().to_string()
+    }
+}
@@ -0,0 +1,91 @@
+use crate::lexer::token::{Position, Span};
+
+use super::{Diagnostic, Label, SourceMap};
+
+/// Escapes a string and wraps it in quotes
+fn string(value: &str) -> String {
+    let mut builder = String::with_capacity(value.len() + 2);
+    builder.push('"');
+
+    for char in value.chars() {
+        match char {
+            '"' => builder.push_str("\\\""),
+            '\\' => builder.push_str("\\\\"),
+            '\n' => builder.push_str("\\n"),
+            '\r' => builder.push_str("\\r"),
+            '\t' => builder.push_str("\\t"),
+            char if char.is_control() => builder.push_str(&format!("\\u{:04x}", char as u32)),
+            char => builder.push(char),
+        }
+    }
+
+    builder.push('"');
+    builder
+}
+
+fn optional_string(value: Option<&str>) -> String {
+    value.map(string).unwrap_or_else(|| String::from("null"))
+}
+
+fn array(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.collect::<Vec<String>>().join(","))
+}
+
+fn position(position: &Position) -> String {
+    format!("{{\"line\":{},\"col\":{},\"offset\":{}}}", position.line, position.col, position.offset)
+}
+
+fn file_name<'a>(span: &Span, sources: &'a SourceMap) -> Option<&'a str> {
+    sources.get(span.file).map(|file| file.name.as_str())
+}
+
+/// Span with the name of its file, or `null` for a span that does not point into any source
+fn span(span: &Span, sources: &SourceMap) -> String {
+    if span.start.line == 0 {
+        return String::from("null");
+    }
+
+    format!(
+        "{{\"file\":{},\"start\":{},\"end\":{}}}",
+        optional_string(file_name(span, sources)),
+        position(&span.start),
+        position(&span.end),
+    )
+}
+
+fn label(label: &Label, sources: &SourceMap) -> String {
+    format!(
+        "{{\"primary\":{},\"message\":{},\"span\":{}}}",
+        label.primary,
+        optional_string(label.message.as_deref()),
+        span(&label.span, sources),
+    )
+}
+
+impl Diagnostic {
+    /// Single line JSON object describing the diagnostic
+    pub fn to_json(&self, sources: &SourceMap) -> String {
+        let primary = self.primary_label();
+
+        let trace = self.trace.iter().map(|frame| format!(
+            "{{\"function\":{},\"call_site\":{}}}",
+            string(&frame.function),
+            span(&frame.call_site, sources),
+        ));
+
+        let fields = [
+            format!("\"type\":{}", string(&self.kind)),
+            format!("\"severity\":{}", string(&self.severity.to_string())),
+            format!("\"code\":{}", optional_string(self.code.as_deref())),
+            format!("\"message\":{}", string(&self.message)),
+            format!("\"file\":{}", optional_string(primary.and_then(|label| file_name(&label.span, sources)))),
+            format!("\"span\":{}", primary.map(|label| span(&label.span, sources)).unwrap_or_else(|| String::from("null"))),
+            format!("\"labels\":{}", array(self.labels.iter().map(|item| label(item, sources)))),
+            format!("\"notes\":{}", array(self.notes.iter().map(|note| string(note)))),
+            format!("\"help\":{}", optional_string(self.help.as_deref())),
+            format!("\"trace\":{}", array(trace)),
+        ];
+
+        format!("{{{}}}", fields.join(","))
+    }
+}
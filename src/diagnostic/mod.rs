@@ -0,0 +1,198 @@
+use std::fmt::Display;
+
+use crate::{errors::{CliError, DynamicError, EvaluatorError, LexerError, ParserError, ParserErrors, ParserWarning, ReplError}, evaluator::trace::{RuntimeError, StackFrame}, lexer::token::{FileId, Span}};
+
+pub mod json;
+pub mod renderer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// A span of source with an optional message explaining its part in the diagnostic
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: Option<String>,
+    pub primary: bool,
+}
+
+impl Label {
+    pub fn primary(span: Span) -> Self {
+        Self { span, message: None, primary: true }
+    }
+
+    pub fn secondary(span: Span) -> Self {
+        Self { span, message: None, primary: false }
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Name of the error the diagnostic was built from, e.g. `ObjectNotFound`
+    pub kind: String,
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+    /// Calls that led to a runtime error, most recent first
+    pub trace: Vec<StackFrame>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            kind: String::from("Error"),
+            severity,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+            trace: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_owned());
+        self
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_kind(mut self, kind: &str) -> Self {
+        self.kind = kind.to_owned();
+        self
+    }
+
+    pub fn with_trace(mut self, trace: Vec<StackFrame>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// The label the diagnostic points at, falling back to the first one
+    pub fn primary_label(&self) -> Option<&Label> {
+        self.labels.iter()
+            .find(|label| label.primary)
+            .or_else(|| self.labels.first())
+    }
+
+    /// Builds a diagnostic for any error, using its own conversion when it has one
+    pub fn from_error(err: &DynamicError) -> Self {
+        if let Some(err) = err.downcast_ref::<RuntimeError>() {
+            return Self::from_error(&err.error).with_trace(err.trace.to_owned());
+        }
+
+        let diagnostic = if let Some(err) = err.downcast_ref::<LexerError>() {
+            err.to_diagnostic()
+        } else if let Some(err) = err.downcast_ref::<ParserError>() {
+            err.to_diagnostic()
+        } else if let Some(err) = err.downcast_ref::<ParserWarning>() {
+            err.to_diagnostic()
+        } else if let Some(err) = err.downcast_ref::<EvaluatorError>() {
+            err.to_diagnostic()
+        } else if let Some(err) = err.downcast_ref::<CliError>() {
+            err.to_diagnostic()
+        } else if let Some(err) = err.downcast_ref::<ReplError>() {
+            err.to_diagnostic()
+        } else {
+            return Diagnostic::error(err.to_string());
+        };
+
+        // The variant name, which is what the derived `Debug` output starts with
+        let name = format!("{:?}", err);
+        let kind = name.split(|char: char| !char.is_alphanumeric()).next().unwrap_or_default();
+        diagnostic.with_kind(kind)
+    }
+
+    /// Builds one diagnostic per error, expanding errors which are made of several
+    pub fn all_from_error(err: &DynamicError) -> Vec<Self> {
+        match err.downcast_ref::<ParserErrors>() {
+            Some(errors) => errors.0.iter().flat_map(Self::all_from_error).collect(),
+            None => vec![Self::from_error(err)],
+        }
+    }
+}
+
+pub trait ToDiagnostic {
+    fn to_diagnostic(&self) -> Diagnostic;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+}
+
+impl SourceFile {
+    /// Text of a 1-based line, without its line ending
+    pub fn line(&self, line: usize) -> Option<&str> {
+        self.source.lines().nth(line.checked_sub(1)?)
+    }
+}
+
+/// Every source the spans of a run may point into, indexed by `FileId`
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+        self.files.push(SourceFile {
+            name: name.into(),
+            source: source.into(),
+        });
+
+        self.files.len() - 1
+    }
+
+    pub fn get(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file)
+    }
+}
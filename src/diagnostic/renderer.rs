@@ -0,0 +1,194 @@
+use std::io::IsTerminal;
+
+use crate::utils::colors::{BLUE, BOLD, CYAN, RED, RESET, YELLOW};
+
+use super::{Diagnostic, Label, Severity, SourceFile, SourceMap};
+
+/// Frames shown at each end of a long backtrace, e.g. from deep recursion
+const TRACE_EDGE_FRAMES: usize = 8;
+
+/// Renders diagnostics with the source lines they point at, underlining each label
+#[derive(Debug, Clone, Copy)]
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    pub fn new(color: bool) -> Self {
+        Self { color }
+    }
+
+    /// Renderer for standard error, colored unless it is redirected or `NO_COLOR` is set
+    pub fn stderr() -> Self {
+        Self::new(std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none())
+    }
+
+    fn paint(&self, text: &str, colors: &[&str]) -> String {
+        if !self.color || text.is_empty() {
+            return text.to_owned();
+        }
+
+        format!("{}{}{}", colors.concat(), text, RESET)
+    }
+
+    fn severity_color(severity: Severity) -> &'static str {
+        match severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Note => CYAN,
+        }
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic, sources: &SourceMap) -> String {
+        let severity_color = Self::severity_color(diagnostic.severity);
+        let mut output = Vec::new();
+
+        let title = match &diagnostic.code {
+            Some(code) => format!("{}[{}]", diagnostic.severity, code),
+            None => diagnostic.severity.to_string(),
+        };
+        output.push(format!("{}{}", self.paint(&title, &[BOLD, severity_color]), self.paint(&format!(": {}", diagnostic.message), &[BOLD])));
+
+        // Labels pointing at unknown files or positions cannot be shown
+        let labels: Vec<(&Label, &SourceFile)> = diagnostic.labels.iter()
+            .filter(|label| label.span.start.line > 0)
+            .filter_map(|label| sources.get(label.span.file).map(|file| (label, file)))
+            .collect();
+
+        let width = labels.iter()
+            .map(|(label, _)| label.span.start.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let gutter = format!("{} {}", " ".repeat(width), self.paint("|", &[BOLD, BLUE]));
+
+        // The primary label's file is shown first, the others follow in order of appearance
+        let mut files: Vec<usize> = Vec::new();
+        if let Some(primary) = diagnostic.primary_label().filter(|label| labels.iter().any(|(other, _)| other == label)) {
+            files.push(primary.span.file);
+        }
+        for (label, _) in &labels {
+            if !files.contains(&label.span.file) {
+                files.push(label.span.file);
+            }
+        }
+
+        for (index, file_id) in files.iter().enumerate() {
+            let file_labels: Vec<&Label> = labels.iter()
+                .filter(|(label, _)| label.span.file == *file_id)
+                .map(|(label, _)| *label)
+                .collect();
+            let file = match sources.get(*file_id) {
+                Some(file) => file,
+                None => continue,
+            };
+
+            let location = file_labels.iter()
+                .find(|label| label.primary)
+                .or(file_labels.first())
+                .map(|label| format!("{}:{}:{}", file.name, label.span.start.line, label.span.start.col))
+                .unwrap_or_default();
+            let arrow = if index == 0 { "-->" } else { ":::" };
+            output.push(format!("{}{} {}", " ".repeat(width), self.paint(arrow, &[BOLD, BLUE]), location));
+            output.push(gutter.to_owned());
+
+            let mut lines: Vec<usize> = file_labels.iter().map(|label| label.span.start.line).collect();
+            lines.sort();
+            lines.dedup();
+
+            let mut previous: Option<usize> = None;
+            for line in lines {
+                if previous.is_some_and(|previous| line > previous + 1) {
+                    output.push(self.paint("...", &[BOLD, BLUE]));
+                }
+                previous = Some(line);
+
+                let text = file.line(line).unwrap_or_default();
+                let number = format!("{:>width$} |", line, width = width);
+                output.push(format!("{} {}", self.paint(&number, &[BOLD, BLUE]), text));
+
+                let mut line_labels: Vec<&&Label> = file_labels.iter().filter(|label| label.span.start.line == line).collect();
+                line_labels.sort_by_key(|label| (!label.primary, label.span.start.col));
+
+                for label in line_labels {
+                    output.push(format!("{} {}", gutter, self.underline(label, text, diagnostic.severity)));
+                }
+            }
+
+            output.push(gutter.to_owned());
+        }
+
+        let padding = " ".repeat(width);
+        for note in &diagnostic.notes {
+            output.push(format!("{} {} {}", padding, self.paint("= note:", &[BOLD]), note));
+        }
+
+        if let Some(help) = &diagnostic.help {
+            output.push(format!("{} {} {}", padding, self.paint("= help:", &[BOLD, CYAN]), help));
+        }
+
+        if !diagnostic.trace.is_empty() {
+            output.push(format!("{} {}", padding, self.paint("= backtrace:", &[BOLD])));
+            output.extend(self.trace(diagnostic, sources).into_iter().map(|line| format!("{}     {}", padding, line)));
+        }
+
+        output.join("\n")
+    }
+
+    /// One line per frame, most recent call first, eliding the middle of long traces
+    fn trace(&self, diagnostic: &Diagnostic, sources: &SourceMap) -> Vec<String> {
+        let trace = &diagnostic.trace;
+        let mut lines = Vec::new();
+
+        for (index, frame) in trace.iter().enumerate() {
+            if trace.len() > TRACE_EDGE_FRAMES * 2 && index >= TRACE_EDGE_FRAMES && index < trace.len() - TRACE_EDGE_FRAMES {
+                if index == TRACE_EDGE_FRAMES {
+                    lines.push(format!("... {} more frames", trace.len() - TRACE_EDGE_FRAMES * 2));
+                }
+
+                continue;
+            }
+
+            let location = match sources.get(frame.call_site.file) {
+                Some(file) if frame.call_site.start.line > 0 => format!("{}:{}", file.name, frame.call_site.start),
+                _ => String::from("unknown location"),
+            };
+
+            lines.push(format!("{}: in '{}', called at {}", index, self.paint(&frame.function, &[BOLD]), location));
+        }
+
+        lines
+    }
+
+    /// Marks under a label's columns, followed by its message
+    fn underline(&self, label: &Label, text: &str, severity: Severity) -> String {
+        let start = label.span.start.col.max(1);
+        let line_end = text.chars().count() + 1;
+        let end = if label.span.end.line == label.span.start.line {
+            label.span.end.col.min(line_end)
+        } else {
+            line_end
+        };
+
+        // Tabs are kept so the marks line up with the source however wide they are displayed
+        let indent: String = text.chars()
+            .take(start - 1)
+            .map(|char| if char == '\t' { '\t' } else { ' ' })
+            .chain(std::iter::repeat(' '))
+            .take(start - 1)
+            .collect();
+
+        let (mark, color) = if label.primary {
+            ('^', Self::severity_color(severity))
+        } else {
+            ('-', BLUE)
+        };
+        let marks = mark.to_string().repeat(end.saturating_sub(start).max(1));
+
+        let underline = match &label.message {
+            Some(message) => format!("{} {}", marks, message),
+            None => marks,
+        };
+
+        format!("{}{}", indent, self.paint(&underline, &[BOLD, color]))
+    }
+}
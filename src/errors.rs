@@ -1,6 +1,6 @@
 use std::{error::Error, fmt::{Debug, Display}};
 
-use crate::{evaluator::object::ObjectType, lexer::token::{Position, TokenType}};
+use crate::{diagnostic::{Diagnostic, Label, ToDiagnostic}, evaluator::object::ObjectType, lexer::token::{Span, TokenType}, parser::ast::Operator};
 
 pub type DynamicError = Box<dyn std::error::Error>;
 
@@ -11,41 +11,197 @@ macro_rules! error {
     };
 }
 
-macro_rules! fmt_pos {
-    ($pos:expr) => {
-        format!("'&_&c{{{{path}}}}:{}:{}&-&r'", $pos.line, $pos.col)
-    };
+/// Lists items as `'a'`, `'a' or 'b'` or `'a', 'b' or 'c'`
+fn one_of<T: Debug>(items: &[T]) -> String {
+    let items: Vec<String> = items.iter().map(|item| format!("'{:?}'", item)).collect();
+
+    match items.split_last() {
+        Some((last, [])) => last.to_owned(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::from("nothing"),
+    }
 }
 
-macro_rules! fmt_token {
-    ($token:expr) => {
-        format!("&g&*{:?}&-&r", $token)
-    };
+// --- CLI Errors ---
+#[derive(Debug, Clone)]
+pub enum CliError {
+    UnknownOption { option: String },
+    MissingArgument { option: String },
+    MissingInput { command: String },
+    UnexpectedArgument { argument: String },
+    InvalidValue { option: String, value: String },
+}
+
+impl Error for CliError {}
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::UnknownOption { option } =>
+                write!(f, "Unknown option '{}'", option),
+            CliError::MissingArgument { option } =>
+                write!(f, "Option '{}' requires an argument", option),
+            CliError::MissingInput { command } =>
+                write!(f, "Command '{}' requires a file, '-' or '-e <code>'", command),
+            CliError::UnexpectedArgument { argument } =>
+                write!(f, "Unexpected argument '{}'", argument),
+            CliError::InvalidValue { option, value } =>
+                write!(f, "Invalid value '{}' for option '{}'", value, option),
+        }
+    }
+}
+
+impl ToDiagnostic for CliError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.to_string())
+            .with_help("run with '--help' to see the available commands and options")
+    }
+}
+
+
+// --- REPL Errors ---
+#[derive(Debug, Clone)]
+pub enum ReplError {
+    UnknownCommand { name: String },
+    MissingArgument { command: String },
 }
 
+impl Error for ReplError {}
+impl Display for ReplError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplError::UnknownCommand { name } =>
+                write!(f, "Unknown command ':{}'", name),
+            ReplError::MissingArgument { command } =>
+                write!(f, "Command ':{}' requires an argument", command),
+        }
+    }
+}
+
+impl ToDiagnostic for ReplError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.to_string())
+            .with_help("use ':help' to see the available commands")
+    }
+}
+
+
 // --- Evaluator Errors ---
 #[derive(Debug, Clone)]
 pub enum EvaluatorError {
-    ObjectNotFound { name: String },
+    ObjectNotFound {
+        name: String,
+        span: Span,
+    },
     InvalidExpression {
-        expected: String
+        expected: String,
+        span: Span,
     },
     InvalidType {
         expected: Vec<ObjectType>,
         found: ObjectType,
+        span: Span,
+    },
+    InvalidOperands {
+        operator: Operator,
+        left: (ObjectType, Span),
+        right: (ObjectType, Span),
+        span: Span,
     },
+    InvalidControlFlow {
+        keyword: String,
+        span: Span,
+    },
+    IndexOutOfBounds {
+        index: i64,
+        length: usize,
+        span: Span,
+    },
+    KeyNotFound {
+        key: String,
+        span: Span,
+    },
+    ZeroStep { span: Span },
+    DivisionByZero { span: Span },
+    IntegerTooLarge { span: Span },
 }
 
 impl Error for EvaluatorError {}
 impl Display for EvaluatorError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            EvaluatorError::ObjectNotFound { name } => 
-                write!(f, "Object '&g&*{}&-&r' not found in current scope", name),
-            EvaluatorError::InvalidType { expected, found } => 
-                write!(f, "Invalid type, expected {}, found {:?}", fmt_token!(expected), fmt_token!(found)),
-            EvaluatorError::InvalidExpression { expected } =>
-                write!(f, "Invalid expression, expected '{:?}'", expected),
+            EvaluatorError::ObjectNotFound { name, .. } =>
+                write!(f, "Object '{}' not found in current scope", name),
+            EvaluatorError::InvalidType { expected, found, .. } =>
+                write!(f, "Invalid type, expected {}, found '{:?}'", one_of(expected), found),
+            EvaluatorError::InvalidExpression { expected, .. } =>
+                write!(f, "Invalid expression, expected '{}'", expected),
+            EvaluatorError::InvalidOperands { operator, left, right, .. } =>
+                write!(f, "Operator '{}' cannot be used for types '{:?}' and '{:?}'", operator, left.0, right.0),
+            EvaluatorError::InvalidControlFlow { keyword, .. } =>
+                write!(f, "'{}' cannot be used where a value is expected", keyword),
+            EvaluatorError::IndexOutOfBounds { index, length, .. } =>
+                write!(f, "Index {} is out of bounds for a list of length {}", index, length),
+            EvaluatorError::KeyNotFound { key, .. } =>
+                write!(f, "Key {} not found in map", key),
+            EvaluatorError::ZeroStep { .. } =>
+                write!(f, "Range step cannot be zero"),
+            EvaluatorError::DivisionByZero { .. } =>
+                write!(f, "Division by zero"),
+            EvaluatorError::IntegerTooLarge { .. } =>
+                write!(f, "Integer is too large to be used here"),
+        }
+    }
+}
+
+impl ToDiagnostic for EvaluatorError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.to_string());
+
+        match self {
+            EvaluatorError::ObjectNotFound { span, .. } => diagnostic
+                .with_code("E0201")
+                .with_label(Label::primary(span.to_owned()).with_message("not found in this scope"))
+                .with_help("names have to be assigned before they are used"),
+            EvaluatorError::InvalidExpression { span, .. } => diagnostic
+                .with_code("E0202")
+                .with_label(Label::primary(span.to_owned())),
+            EvaluatorError::InvalidType { expected, found, span } => diagnostic
+                .with_code("E0203")
+                .with_label(Label::primary(span.to_owned()).with_message(format!("this has type '{:?}'", found)))
+                .with_note(format!("expected {}", one_of(expected))),
+            EvaluatorError::InvalidOperands { left, right, span, .. } => diagnostic
+                .with_code("E0204")
+                .with_label(Label::primary(span.to_owned()))
+                .with_label(Label::secondary(left.1.to_owned()).with_message(format!("this has type '{:?}'", left.0)))
+                .with_label(Label::secondary(right.1.to_owned()).with_message(format!("this has type '{:?}'", right.0))),
+            EvaluatorError::InvalidControlFlow { keyword, span } => diagnostic
+                .with_code("E0205")
+                .with_label(Label::primary(span.to_owned()).with_message(format!("'{}' used inside an expression", keyword))),
+            EvaluatorError::IndexOutOfBounds { length, span, .. } => {
+                let diagnostic = diagnostic
+                    .with_code("E0206")
+                    .with_label(Label::primary(span.to_owned()).with_message("index out of bounds"));
+
+                match length {
+                    0 => diagnostic.with_note("the list is empty"),
+                    length => diagnostic.with_note(format!("valid indices are 0 to {}, or -{} to -1 counting from the end", length - 1, length)),
+                }
+            },
+            EvaluatorError::KeyNotFound { span, .. } => diagnostic
+                .with_code("E0207")
+                .with_label(Label::primary(span.to_owned()).with_message("no entry with this key"))
+                .with_help("use 'has(map, key)' to check whether a key exists"),
+            EvaluatorError::ZeroStep { span } => diagnostic
+                .with_code("E0208")
+                .with_label(Label::primary(span.to_owned()).with_message("this step is zero"))
+                .with_help("use a negative step to count down"),
+            EvaluatorError::DivisionByZero { span } => diagnostic
+                .with_code("E0209")
+                .with_label(Label::primary(span.to_owned()).with_message("this is zero")),
+            EvaluatorError::IntegerTooLarge { span } => diagnostic
+                .with_code("E0210")
+                .with_label(Label::primary(span.to_owned()).with_message("this is too large"))
+                .with_note("integers of any size can be used in arithmetic, but indices and range bounds have to fit in 64 bits, exponents in 32 bits, and powers can have at most 2^18 bits"),
         }
     }
 }
@@ -56,34 +212,101 @@ impl Display for EvaluatorError {
 pub enum ParserError {
     UnexpectedToken {
         found: TokenType,
-        pos: Position
+        span: Span,
     },
-    
+
     InvalidToken {
         expected: Vec<TokenType>,
         found: TokenType,
-        pos: Position
+        span: Span,
     },
 
-    InvalidStatement,
-    OutOfBounds { index: String },
+    InvalidStatement { span: Span },
+    OutsideLoop {
+        keyword: String,
+        span: Span,
+    },
 }
 
 impl Error for ParserError {}
 impl Display for ParserError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParserError::UnexpectedToken { found, pos } => 
-                write!(f, "Unexpected token {} at {}", fmt_token!(found), fmt_pos!(pos)),
+            ParserError::UnexpectedToken { found, .. } =>
+                write!(f, "Unexpected token '{:?}'", found),
 
-            ParserError::InvalidToken { expected, found, pos } => 
-                write!(f, "Token {} was found at {}, expected {}", fmt_token!(found), fmt_pos!(pos), fmt_token!(expected)),
-                
-            ParserError::OutOfBounds { index } => 
-                write!(f, "Out of bounds for index &c{}", index),
+            ParserError::InvalidToken { expected, found, .. } =>
+                write!(f, "Token '{:?}' was found, expected {}", found, one_of(expected)),
 
-            ParserError::InvalidStatement =>
+            ParserError::InvalidStatement { .. } =>
                 write!(f, "Invalid statement"),
+
+            ParserError::OutsideLoop { keyword, .. } =>
+                write!(f, "'{}' outside of a loop", keyword),
+        }
+    }
+}
+
+/// Every error found by a parse that recovered from them
+#[derive(Debug)]
+pub struct ParserErrors(pub Vec<DynamicError>);
+
+impl Error for ParserErrors {}
+impl Display for ParserErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Found {} syntax errors", self.0.len())
+    }
+}
+
+// --- Parser Warnings ---
+#[derive(Debug, Clone)]
+pub enum ParserWarning {
+    UnreachableCode {
+        keyword: String,
+        span: Span,
+    },
+}
+
+impl Error for ParserWarning {}
+impl Display for ParserWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParserWarning::UnreachableCode { keyword, .. } =>
+                write!(f, "Unreachable code after '{}'", keyword),
+        }
+    }
+}
+
+impl ToDiagnostic for ParserWarning {
+    fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::warning(self.to_string());
+
+        match self {
+            ParserWarning::UnreachableCode { keyword, span } => diagnostic
+                .with_code("W0101")
+                .with_label(Label::primary(span.to_owned()).with_message("this code never runs"))
+                .with_help(format!("remove it, or move it before the '{}'", keyword)),
+        }
+    }
+}
+
+impl ToDiagnostic for ParserError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.to_string());
+
+        match self {
+            ParserError::UnexpectedToken { span, .. } => diagnostic
+                .with_code("E0101")
+                .with_label(Label::primary(span.to_owned()).with_message("unexpected token")),
+            ParserError::InvalidToken { expected, span, .. } => diagnostic
+                .with_code("E0102")
+                .with_label(Label::primary(span.to_owned()).with_message(format!("expected {}", one_of(expected)))),
+            ParserError::InvalidStatement { span } => diagnostic
+                .with_code("E0103")
+                .with_label(Label::primary(span.to_owned())),
+            ParserError::OutsideLoop { span, .. } => diagnostic
+                .with_code("E0105")
+                .with_label(Label::primary(span.to_owned()).with_message("cannot be used outside of a loop")),
         }
     }
 }
@@ -95,18 +318,72 @@ pub enum LexerError {
     OutOfBounds { index: String },
     InvalidCharacter {
         character: char,
-        pos: Position
+        span: Span,
     },
+    UnterminatedString { span: Span },
+    IntegerTooLarge { span: Span },
+    InvalidNumber { reason: String, span: Span },
+    UnterminatedInterpolation { span: Span },
+    InvalidEscape { reason: String, span: Span },
+    UnterminatedComment { span: Span },
 }
 
 impl Error for LexerError {}
 impl Display for LexerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            LexerError::OutOfBounds { index } => 
+            LexerError::OutOfBounds { index } =>
                 write!(f, "Out of bounds at index {}", index),
-            LexerError::InvalidCharacter { character, pos } => 
-                write!(f, "Invalid character '{}' at {:?}", character, fmt_pos!(pos)),
+            LexerError::InvalidCharacter { character, .. } =>
+                write!(f, "Invalid character '{}'", character),
+            LexerError::UnterminatedString { .. } =>
+                write!(f, "Unterminated string"),
+            LexerError::IntegerTooLarge { .. } =>
+                write!(f, "Integer literal is too large"),
+            LexerError::InvalidNumber { .. } =>
+                write!(f, "Invalid number literal"),
+            LexerError::UnterminatedInterpolation { .. } =>
+                write!(f, "Unterminated interpolation"),
+            LexerError::InvalidEscape { .. } =>
+                write!(f, "Invalid escape sequence"),
+            LexerError::UnterminatedComment { .. } =>
+                write!(f, "Unterminated block comment"),
+        }
+    }
+}
+
+impl ToDiagnostic for LexerError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.to_string());
+
+        match self {
+            LexerError::OutOfBounds { .. } => diagnostic
+                .with_code("E0001"),
+            LexerError::InvalidCharacter { span, .. } => diagnostic
+                .with_code("E0002")
+                .with_label(Label::primary(span.to_owned())),
+            LexerError::UnterminatedString { span } => diagnostic
+                .with_code("E0003")
+                .with_label(Label::primary(span.to_owned()).with_message("string starts here"))
+                .with_help("end the string with the same quotes it starts with"),
+            LexerError::IntegerTooLarge { span } => diagnostic
+                .with_code("E0004")
+                .with_label(Label::primary(span.to_owned()).with_message("this does not fit in 64 bits"))
+                .with_help("larger integers can be computed from smaller ones, e.g. '2 ^ 100'"),
+            LexerError::InvalidNumber { reason, span } => diagnostic
+                .with_code("E0005")
+                .with_label(Label::primary(span.to_owned()).with_message(reason)),
+            LexerError::UnterminatedInterpolation { span } => diagnostic
+                .with_code("E0006")
+                .with_label(Label::primary(span.to_owned()).with_message("interpolation starts here"))
+                .with_help("add a closing '}' to end the interpolation, or write '\\${' for a literal '${'"),
+            LexerError::InvalidEscape { reason, span } => diagnostic
+                .with_code("E0007")
+                .with_label(Label::primary(span.to_owned()).with_message(reason)),
+            LexerError::UnterminatedComment { span } => diagnostic
+                .with_code("E0008")
+                .with_label(Label::primary(span.to_owned()).with_message("comment starts here"))
+                .with_help("add a closing ']#' to end the comment, block comments can be nested"),
         }
     }
 }
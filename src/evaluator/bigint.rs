@@ -0,0 +1,427 @@
+use std::{cmp::Ordering, fmt::Display, ops::{Add, Div, Mul, Neg, Rem, Sub}};
+
+/// Base of the digits of a `BigInt`
+const BASE: u64 = 1 << 32;
+
+/// Integer of any size, used for the results of integer arithmetic which do not fit in an `i64`
+///
+/// Division and remainder truncate towards zero, like they do for `i64`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    /// Magnitude in base 2^32, least significant digit first and without leading zeros
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self { negative: false, digits: Vec::new() }
+    }
+
+    fn from_parts(negative: bool, mut digits: Vec<u32>) -> Self {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+
+        // There is no negative zero
+        let negative = negative && !digits.is_empty();
+        Self { negative, digits }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Digits of the magnitude in base 2^32, least significant first
+    pub fn digits(&self) -> &[u32] {
+        &self.digits
+    }
+
+    /// Number of bits of the magnitude, which is 0 for zero
+    pub fn bits(&self) -> u64 {
+        match self.digits.last() {
+            Some(last) => (self.digits.len() as u64 - 1) * 32 + (32 - last.leading_zeros() as u64),
+            None => 0,
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.digits.len() > 2 {
+            return None;
+        }
+
+        let magnitude = self.digits.iter().rev().fold(0u64, |acc, digit| acc << 32 | *digit as u64);
+        match self.negative {
+            true => 0i64.checked_sub_unsigned(magnitude),
+            false => i64::try_from(magnitude).ok(),
+        }
+    }
+
+    /// Closest float to the integer, which is infinite when the integer is too large
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self.digits.iter().rev().fold(0f64, |acc, digit| acc * BASE as f64 + *digit as f64);
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> Self {
+        let mut result = BigInt::from(1);
+        let mut base = self.to_owned();
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+
+        result
+    }
+
+    /// Quotient and remainder of the division by another integer, which must not be zero
+    fn div_rem(&self, other: &Self) -> (Self, Self) {
+        assert!(!other.is_zero(), "Division of a big integer by zero");
+
+        let (quotient, remainder) = div_rem_magnitude(&self.digits, &other.digits);
+        (
+            Self::from_parts(self.negative != other.negative, quotient),
+            Self::from_parts(self.negative, remainder),
+        )
+    }
+
+    /// Divides the magnitude by a single digit in place, returning the remainder
+    fn div_rem_digit(digits: &mut [u32], divisor: u32) -> u32 {
+        let mut remainder = 0u64;
+
+        for digit in digits.iter_mut().rev() {
+            let current = remainder << 32 | *digit as u64;
+            *digit = (current / divisor as u64) as u32;
+            remainder = current % divisor as u64;
+        }
+
+        remainder as u32
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        Self::from_parts(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+fn cmp_magnitude(lhs: &[u32], rhs: &[u32]) -> Ordering {
+    lhs.len().cmp(&rhs.len()).then_with(|| lhs.iter().rev().cmp(rhs.iter().rev()))
+}
+
+fn add_magnitude(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(lhs.len().max(rhs.len()) + 1);
+    let mut carry = 0u64;
+
+    for position in 0..lhs.len().max(rhs.len()) {
+        let sum = *lhs.get(position).unwrap_or(&0) as u64 + *rhs.get(position).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+
+    result.push(carry as u32);
+    result
+}
+
+/// Subtracts a magnitude from a larger or equal one
+fn sub_magnitude(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(lhs.len());
+    let mut borrow = 0i64;
+
+    for (position, digit) in lhs.iter().enumerate() {
+        let mut difference = *digit as i64 - *rhs.get(position).unwrap_or(&0) as i64 - borrow;
+        borrow = (difference < 0) as i64;
+        if difference < 0 {
+            difference += BASE as i64;
+        }
+
+        result.push(difference as u32);
+    }
+
+    result
+}
+
+fn mul_magnitude(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; lhs.len() + rhs.len()];
+
+    for (i, left) in lhs.iter().enumerate() {
+        let mut carry = 0u64;
+
+        for (j, right) in rhs.iter().enumerate() {
+            let current = result[i + j] as u64 + *left as u64 * *right as u64 + carry;
+            result[i + j] = current as u32;
+            carry = current >> 32;
+        }
+
+        result[i + rhs.len()] = carry as u32;
+    }
+
+    result
+}
+
+/// Long division going through the dividend one bit at a time
+fn div_rem_magnitude(dividend: &[u32], divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if let [divisor] = divisor {
+        let mut quotient = dividend.to_vec();
+        let remainder = BigInt::div_rem_digit(&mut quotient, *divisor);
+        return (quotient, vec![remainder]);
+    }
+
+    let mut quotient = vec![0u32; dividend.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+
+    for bit in (0..dividend.len() * 32).rev() {
+        // remainder = remainder * 2 + the current bit of the dividend
+        let mut carry = (dividend[bit / 32] >> (bit % 32)) & 1;
+        for digit in remainder.iter_mut() {
+            let next = *digit >> 31;
+            *digit = *digit << 1 | carry;
+            carry = next;
+        }
+        if carry != 0 {
+            remainder.push(carry);
+        }
+
+        if cmp_magnitude(&remainder, divisor) != Ordering::Less {
+            remainder = sub_magnitude(&remainder, divisor);
+            while remainder.last() == Some(&0) {
+                remainder.pop();
+            }
+
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+
+    (quotient, remainder)
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: Self) -> BigInt {
+        if self.negative == rhs.negative {
+            return BigInt::from_parts(self.negative, add_magnitude(&self.digits, &rhs.digits));
+        }
+
+        // Opposite signs, the result has the sign of the larger magnitude
+        match cmp_magnitude(&self.digits, &rhs.digits) {
+            Ordering::Less => BigInt::from_parts(rhs.negative, sub_magnitude(&rhs.digits, &self.digits)),
+            _ => BigInt::from_parts(self.negative, sub_magnitude(&self.digits, &rhs.digits)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: Self) -> BigInt {
+        self + &-rhs
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: Self) -> BigInt {
+        BigInt::from_parts(self.negative != rhs.negative, mul_magnitude(&self.digits, &rhs.digits))
+    }
+}
+
+impl Div for &BigInt {
+    type Output = BigInt;
+
+    fn div(self, rhs: Self) -> BigInt {
+        self.div_rem(rhs).0
+    }
+}
+
+impl Rem for &BigInt {
+    type Output = BigInt;
+
+    fn rem(self, rhs: Self) -> BigInt {
+        self.div_rem(rhs).1
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.digits.to_owned())
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.digits, &other.digits),
+            (true, true) => cmp_magnitude(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Split into chunks of nine decimal digits, least significant first
+        let mut digits = self.digits.to_owned();
+        let mut chunks = Vec::new();
+        while digits.iter().any(|digit| *digit != 0) {
+            chunks.push(BigInt::div_rem_digit(&mut digits, 1_000_000_000));
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            write!(f, "{}", first)?;
+        }
+
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BigInt;
+
+    fn int(value: i64) -> BigInt {
+        BigInt::from(value)
+    }
+
+    fn two_to(exponent: u32) -> BigInt {
+        int(2).pow(exponent)
+    }
+
+    #[test]
+    fn formats_powers_of_two() {
+        assert_eq!(two_to(32).to_string(), "4294967296");
+        assert_eq!(two_to(64).to_string(), "18446744073709551616");
+        assert_eq!(two_to(100).to_string(), "1267650600228229401496703205376");
+        assert_eq!(int(3).pow(100).to_string(), "515377520732011331036461129765621272702107522001");
+    }
+
+    #[test]
+    fn formats_zero_chunks() {
+        assert_eq!(BigInt::zero().to_string(), "0");
+        assert_eq!(int(10).pow(18).to_string(), "1000000000000000000");
+        assert_eq!((&int(10).pow(27) + &int(7)).to_string(), "1000000000000000000000000007");
+        assert_eq!((-&int(10).pow(18)).to_string(), "-1000000000000000000");
+    }
+
+    #[test]
+    fn adds_across_limbs() {
+        assert_eq!(&int(u32::MAX as i64) + &int(1), two_to(32));
+        assert_eq!((&int(i64::MAX) + &int(1)).to_string(), "9223372036854775808");
+        assert_eq!(&(&two_to(64) - &int(1)) + &int(1), two_to(64));
+    }
+
+    #[test]
+    fn subtracts_across_limbs() {
+        assert_eq!((&two_to(100) - &int(1)).to_string(), "1267650600228229401496703205375");
+        assert_eq!((&two_to(64) - &int(u32::MAX as i64 + 2)).to_string(), "18446744069414584319");
+        assert_eq!((&two_to(64) - &two_to(64)), BigInt::zero());
+    }
+
+    #[test]
+    fn multiplies_across_limbs() {
+        let max = &two_to(64) - &int(1);
+        assert_eq!((&max * &max).to_string(), "340282366920938463426481119284349108225");
+        assert_eq!(&two_to(40) * &two_to(60), two_to(100));
+        assert!((&two_to(100) * &BigInt::zero()).is_zero());
+    }
+
+    #[test]
+    fn divides_across_limbs() {
+        assert_eq!((&two_to(100) / &int(3)).to_string(), "422550200076076467165567735125");
+        assert_eq!(&two_to(100) % &int(3), int(1));
+        assert_eq!(&two_to(100) / &two_to(36), two_to(64));
+        assert!((&two_to(36) / &two_to(100)).is_zero());
+    }
+
+    #[test]
+    fn handles_negative_operands() {
+        let negative = -&two_to(100);
+        assert!(negative.is_negative());
+        assert_eq!(negative.to_string(), "-1267650600228229401496703205376");
+        assert_eq!(&negative + &two_to(100), BigInt::zero());
+        assert_eq!(&int(-5) - &two_to(64), -&(&two_to(64) + &int(5)));
+        assert_eq!(&negative * &negative, two_to(200));
+        assert!(negative < int(i64::MIN));
+    }
+
+    #[test]
+    fn division_truncates_towards_zero() {
+        let negative = -&two_to(100);
+        assert_eq!((&negative / &int(7)).to_string(), "-181092942889747057356671886482");
+        assert_eq!(&negative % &int(7), int(-2));
+
+        let divisor = -&(&two_to(40) + &int(5));
+        assert_eq!(&two_to(100) / &divisor, int(-1152921504601604096));
+        assert_eq!(&two_to(100) % &divisor, int(26214400));
+
+        let divisor = &two_to(64) + &int(1);
+        assert_eq!(&(-&two_to(96)) / &divisor, int(-4294967295));
+        assert_eq!((&(-&two_to(96)) % &divisor).to_string(), "-18446744069414584321");
+    }
+
+    #[test]
+    fn counts_bits() {
+        assert_eq!(BigInt::zero().bits(), 0);
+        assert_eq!(int(1).bits(), 1);
+        assert_eq!(int(-255).bits(), 8);
+        assert_eq!(two_to(32).bits(), 33);
+        assert_eq!((&two_to(64) - &int(1)).bits(), 64);
+        assert_eq!(two_to(100).bits(), 101);
+    }
+
+    #[test]
+    fn converts_to_i64_at_the_limits() {
+        assert_eq!(int(i64::MIN).to_string(), "-9223372036854775808");
+        assert_eq!(int(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!(int(i64::MAX).to_i64(), Some(i64::MAX));
+        assert_eq!((&int(i64::MAX) + &int(1)).to_i64(), None);
+        assert_eq!((&int(i64::MIN) - &int(1)).to_i64(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Division of a big integer by zero")]
+    fn division_by_zero_panics() {
+        let _ = &two_to(100) / &BigInt::zero();
+    }
+
+    #[test]
+    #[should_panic(expected = "Division of a big integer by zero")]
+    fn remainder_by_zero_panics() {
+        let _ = &int(1) % &BigInt::zero();
+    }
+}
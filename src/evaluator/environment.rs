@@ -1,46 +1,131 @@
+use std::{cell::RefCell, collections::HashSet, fmt::Debug, rc::Rc};
+
 use super::object::Object;
 
-#[derive(Clone, Debug)]
+/// Names bound in a single scope, along with the scope it is nested in
+#[derive(Default)]
+struct Frame<'a> {
+    bindings: RefCell<Vec<(&'a str, Object)>>,
+    parent: Option<Rc<Frame<'a>>>,
+}
+
+impl<'a> Frame<'a> {
+    fn get(&self, identifier: &str) -> Option<Object> {
+        self.bindings.borrow().iter()
+            .find(|(name, _)| *name == identifier)
+            .map(|(_, object)| object.to_owned())
+    }
+
+    /// Replaces the value of an existing binding, returning whether there was one
+    fn replace(&self, identifier: &str, object: &Object) -> bool {
+        match self.bindings.borrow_mut().iter_mut().find(|(name, _)| *name == identifier) {
+            Some((_, value)) => {
+                *value = object.to_owned();
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+/// A chain of scopes, from the innermost one out to the global scope
+///
+/// Clones share their scopes, which is how closures keep the environment they were declared in alive.
+#[derive(Clone)]
 pub struct Environment<'a> {
-    name_store: Vec<&'a str>,
-    value_store: Vec<Object>,
+    frame: Rc<Frame<'a>>,
+}
+
+impl Debug for Environment<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut map = f.debug_map();
+
+        for (name, value) in self.bindings() {
+            map.entry(&format_args!("{}", name), &format_args!("{}", value.to_string_with_type()));
+        }
+
+        map.finish()
+    }
+}
+
+impl Default for Environment<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<'a> Environment<'a> {
     pub fn new() -> Self {
         Self {
-            name_store: Vec::with_capacity(64),
-            value_store: Vec::with_capacity(64),
+            frame: Rc::new(Frame::default()),
         }
     }
 
-    pub fn size(&self) -> usize {
-        self.name_store.len()
+    /// A new scope nested in this one
+    pub fn child(&self) -> Self {
+        Self {
+            frame: Rc::new(Frame {
+                bindings: RefCell::default(),
+                parent: Some(Rc::clone(&self.frame)),
+            }),
+        }
     }
 
-    pub fn truncate(&mut self, size: usize) {
-        self.name_store.truncate(size);
-        self.value_store.truncate(size);
+    fn frames(&self) -> impl Iterator<Item = &Frame<'a>> {
+        std::iter::successors(Some(self.frame.as_ref()), |frame| frame.parent.as_deref())
+    }
+
+    /// Binds the name in the innermost scope, shadowing any outer binding
+    pub fn define(&mut self, identifier: &'a str, object: Object) {
+        if !self.frame.replace(identifier, &object) {
+            self.frame.bindings.borrow_mut().push((identifier, object));
+        }
     }
 
+    /// Assigns to the nearest existing binding, or binds the name in the innermost scope if there is none
     pub fn set(&mut self, identifier: &'a str, object: Object) {
-        if self.name_store.contains(&identifier) {
-            if let Some(pos) = self.name_store.iter().rev().position(|name| *name == identifier) {
-                let index = self.value_store.len() - 1 - pos;
-                self.value_store[index] = object;
-                return;
-            }
+        if self.frames().any(|frame| frame.replace(identifier, &object)) {
+            return;
         }
 
-        self.name_store.push(identifier);
-        self.value_store.push(object);
+        self.define(identifier, object);
+    }
+
+    pub fn get(&self, identifier: &str) -> Option<Object> {
+        self.frames().find_map(|frame| frame.get(identifier))
     }
 
-    pub fn get(&self, identifier: &String) -> Option<&Object> {
-        if let Some(pos) = self.name_store.iter().rev().position(|name| name == identifier) {
-            return Some(&self.value_store[self.value_store.len() - 1 - pos]);
+    /// Every visible binding, outermost scope first, without the ones that are shadowed
+    pub fn bindings(&self) -> Vec<(&'a str, Object)> {
+        let mut bindings: Vec<(&'a str, Object)> = Vec::new();
+
+        for frame in self.frames() {
+            for (name, object) in frame.bindings.borrow().iter().rev() {
+                if !bindings.iter().any(|(other, _)| other == name) {
+                    bindings.push((name, object.to_owned()));
+                }
+            }
         }
 
-        None
+        bindings.reverse();
+        bindings
+    }
+
+    pub fn names(&self) -> Vec<&'a str> {
+        self.bindings().into_iter().map(|(name, _)| name).collect()
+    }
+
+    /// Adds the objects bound in every scope of the chain to `objects`, skipping the scopes in `visited`
+    ///
+    /// Scopes are shared by closures, so the collector keeps track of the ones it has already gone through.
+    pub fn trace(&self, visited: &mut HashSet<*const ()>, objects: &mut Vec<Object>) {
+        for frame in self.frames() {
+            if !visited.insert(frame as *const Frame as *const ()) {
+                // The outer scopes were gone through along with this one
+                break;
+            }
+
+            objects.extend(frame.bindings.borrow().iter().map(|(_, object)| object.to_owned()));
+        }
     }
 }
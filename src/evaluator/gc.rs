@@ -0,0 +1,262 @@
+use std::{cell::RefCell, collections::HashSet};
+
+use super::{environment::Environment, object::{Header, Object, ObjectType}};
+
+/// Bytes that can be allocated after a collection before the next one starts
+pub const DEFAULT_THRESHOLD: usize = 1024 * 1024;
+
+thread_local! {
+    static HEAP: RefCell<Heap> = RefCell::new(Heap::new(DEFAULT_THRESHOLD));
+}
+
+/// Every object allocated on the heap, so the ones that are no longer reachable can be freed
+struct Heap {
+    objects: Vec<Object>,
+    /// Size of the objects, kept up to date as they are allocated, resized and collected
+    bytes: usize,
+    threshold: usize,
+    /// Size the heap has to reach for the next collection to start
+    next_collection: usize,
+    collections: usize,
+    freed_objects: usize,
+    freed_bytes: usize,
+}
+
+impl Heap {
+    fn new(threshold: usize) -> Self {
+        Self {
+            objects: Vec::new(),
+            bytes: 0,
+            threshold,
+            next_collection: threshold,
+            collections: 0,
+            freed_objects: 0,
+            freed_bytes: 0,
+        }
+    }
+}
+
+/// Counters describing the heap, as returned by the `gc_stats` native function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GcStats {
+    pub collections: usize,
+    pub live_objects: usize,
+    pub live_bytes: usize,
+    pub freed_objects: usize,
+    pub freed_bytes: usize,
+    pub threshold: usize,
+}
+
+/// Sets how many bytes can be allocated after a collection before the next one starts
+pub fn set_threshold(threshold: usize) {
+    HEAP.with_borrow_mut(|heap| {
+        heap.threshold = threshold;
+        heap.next_collection = heap.bytes + threshold;
+    });
+}
+
+/// Adds a newly allocated object to the heap, it is freed by the first collection it is not reachable during
+pub fn track(object: &Object) {
+    HEAP.with_borrow_mut(|heap| {
+        heap.bytes += object.heap_size();
+        heap.objects.push(object.to_owned());
+    });
+}
+
+/// Accounts for a tracked object having grown or shrunk in place, given its size beforehand
+pub fn resize(object: &Object, before: usize) {
+    HEAP.with_borrow_mut(|heap| heap.bytes = (heap.bytes + object.heap_size()).saturating_sub(before));
+}
+
+/// Whether enough has been allocated since the last collection to start a new one
+pub fn should_collect() -> bool {
+    HEAP.with_borrow(|heap| heap.bytes >= heap.next_collection)
+}
+
+pub fn stats() -> GcStats {
+    HEAP.with_borrow(|heap| GcStats {
+        collections: heap.collections,
+        live_objects: heap.objects.len(),
+        live_bytes: heap.bytes,
+        freed_objects: heap.freed_objects,
+        freed_bytes: heap.freed_bytes,
+        threshold: heap.threshold,
+    })
+}
+
+/// Frees every object which cannot be reached from the roots or the bindings of the environments
+///
+/// Anything the caller holds onto outside of these is freed as well, so it has to be passed as a root.
+pub fn collect<'e, 'a: 'e>(roots: &[Object], environments: impl IntoIterator<Item = &'e Environment<'a>>) {
+    let mut frames = HashSet::new();
+    let mut pending = roots.to_vec();
+    for env in environments {
+        env.trace(&mut frames, &mut pending);
+    }
+
+    // Mark
+    while let Some(object) = pending.pop() {
+        if !object.is_heap() {
+            continue;
+        }
+
+        let header = unsafe { Header::read(&object) };
+        if header.marked {
+            continue;
+        }
+        header.marked = true;
+
+        match object.get_type() {
+            ObjectType::List => pending.extend(object.as_list().expect("Couldn't take as list").iter().cloned()),
+            ObjectType::Map => pending.extend(object.as_map().expect("Couldn't take as map").values().cloned()),
+            ObjectType::Function => object.as_function().expect("Couldn't take as function").env.trace(&mut frames, &mut pending),
+            _ => {},
+        }
+    }
+
+    // Sweep
+    HEAP.with_borrow_mut(|heap| {
+        let mut live_bytes = 0;
+        let mut freed_objects = 0;
+        let mut freed_bytes = 0;
+
+        heap.objects.retain(|object| {
+            let header = unsafe { Header::read(object) };
+            if header.marked {
+                header.marked = false;
+                live_bytes += object.heap_size();
+                return true;
+            }
+
+            freed_objects += 1;
+            freed_bytes += object.heap_size();
+            object.to_owned().free();
+            false
+        });
+
+        heap.collections += 1;
+        heap.freed_objects += freed_objects;
+        heap.freed_bytes += freed_bytes;
+        heap.bytes = live_bytes;
+        heap.next_collection = live_bytes + heap.threshold;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{evaluator::Evaluator, lexer::Lexer, parser::{ast::Node, Parser}};
+    use super::{set_threshold, stats};
+
+    /// Runs a program collecting garbage before every statement, returning its result as text
+    fn run(source: &str) -> String {
+        set_threshold(1);
+
+        let mut lexer = Lexer::from(source);
+        let tokens = lexer.tokenize().unwrap_or_else(|err| panic!("Could not lex: {}", err));
+        let Node::Program(ast) = Parser::from(tokens).parse().unwrap_or_else(|err| panic!("Could not parse: {}", err)) else {
+            panic!("AST is not a program node");
+        };
+
+        let mut evaluator = Evaluator::new(&ast);
+        let result = evaluator.eval().unwrap_or_else(|err| panic!("Could not run: {}", err));
+        result.to_string()
+    }
+
+    #[test]
+    fn frees_unreachable_objects() {
+        let result = run("
+            for i in 0..20 {
+                garbage = [i, \"${i}\"];
+            }
+
+            kept = [\"kept\"];
+            for i in 0..20 {
+                garbage = {\"index\": i};
+            }
+
+            return kept[0];
+        ");
+
+        assert_eq!(result, "kept");
+        let stats = stats();
+        assert!(stats.collections > 0);
+        assert!(stats.freed_objects >= 40, "freed {} objects", stats.freed_objects);
+        assert!(stats.live_objects < 20, "{} objects are still alive", stats.live_objects);
+    }
+
+    #[test]
+    fn keeps_environments_captured_by_closures() {
+        let result = run("
+            make_counter = (prefix) {
+                count = 0;
+                return () {
+                    count += 1;
+                    return \"${prefix}${count}\";
+                };
+            };
+
+            counter = make_counter(\"count \");
+            make_counter = null;
+            for i in 0..30 {
+                garbage = [i];
+                counter();
+            }
+
+            return counter();
+        ");
+
+        assert_eq!(result, "count 31");
+        assert!(stats().freed_objects > 0);
+    }
+
+    #[test]
+    fn keeps_containers_reachable_through_other_containers() {
+        let result = run("
+            root = {\"lists\": [[1, \"one\"], {\"name\": \"nested\"}]};
+            inner = [[\"deep\"]];
+            push(root[\"lists\"], inner);
+            inner = null;
+
+            for i in 0..30 {
+                garbage = [[i], {\"i\": i}];
+            }
+
+            lists = root[\"lists\"];
+            return \"${lists[0][1]} ${lists[1][\"name\"]} ${lists[2][0][0]}\";
+        ");
+
+        assert_eq!(result, "one nested deep");
+        assert!(stats().freed_objects > 0);
+    }
+
+    #[test]
+    fn keeps_operands_while_evaluating_the_rest() {
+        // The left operands and arguments are only held by the evaluator while the calls run statements
+        let result = run("
+            make = (text) {
+                garbage = [text, text];
+                return text;
+            };
+
+            pair = [make(\"a\"), make(\"b\") + make(\"c\")];
+            return pair[0] + pair[1];
+        ");
+
+        assert_eq!(result, "abc");
+    }
+
+    #[test]
+    fn accounts_for_lists_growing_in_place() {
+        let result = run("
+            list = [];
+            for i in 0..100 {
+                push(list, i);
+            }
+
+            return len(list);
+        ");
+
+        assert_eq!(result, "100");
+        assert!(stats().live_bytes >= 100 * std::mem::size_of::<crate::evaluator::object::Object>());
+    }
+}
@@ -0,0 +1,78 @@
+use super::{map::MapKey, object::{Object, ObjectType}, range::Range};
+
+/// Goes through the items of a collection for a `for ... in` loop
+///
+/// Every step yields a key and a value: the position and the item for lists, strings and ranges,
+/// or the key and its value for maps.
+pub enum ObjectIterator {
+    /// Lists are read as the loop goes, so items pushed while looping are included
+    List { list: Object, position: usize },
+    String { chars: Vec<char>, position: usize },
+    /// The keys are taken when the loop starts, keys removed while looping are skipped
+    Map { map: Object, keys: Vec<MapKey>, position: usize },
+    Range { range: Range, position: usize },
+}
+
+impl ObjectIterator {
+    /// Types whose objects can be iterated
+    pub const TYPES: [ObjectType; 4] = [ObjectType::List, ObjectType::String, ObjectType::Map, ObjectType::Range];
+
+    pub fn new(object: &Object) -> Option<Self> {
+        Some(match object.get_type() {
+            ObjectType::List => ObjectIterator::List { list: object.to_owned(), position: 0 },
+            ObjectType::String => ObjectIterator::String { chars: object.as_str()?.chars().collect(), position: 0 },
+            ObjectType::Map => ObjectIterator::Map {
+                map: object.to_owned(),
+                keys: object.as_map()?.keys().cloned().collect(),
+                position: 0,
+            },
+            ObjectType::Range => ObjectIterator::Range { range: object.as_range()?.to_owned(), position: 0 },
+            _ => return None,
+        })
+    }
+
+    /// Whether a loop with a single variable gets the keys rather than the values, which is the case for maps
+    pub fn yields_keys(&self) -> bool {
+        matches!(self, ObjectIterator::Map { .. })
+    }
+}
+
+impl Iterator for ObjectIterator {
+    type Item = (Object, Object);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            ObjectIterator::List { list, position } => {
+                let item = list.as_list()?.get(*position)?.to_owned();
+                *position += 1;
+
+                Some((Object::integer(*position as i64 - 1), item))
+            },
+            ObjectIterator::String { chars, position } => {
+                let char = chars.get(*position)?;
+                *position += 1;
+
+                Some((Object::integer(*position as i64 - 1), Object::string(&char.to_string())))
+            },
+            ObjectIterator::Map { map, keys, position } => {
+                let map = map.as_map()?;
+
+                while let Some(key) = keys.get(*position) {
+                    *position += 1;
+
+                    if let Some(value) = map.get(key) {
+                        return Some((key.to_object(), value.to_owned()));
+                    }
+                }
+
+                None
+            },
+            ObjectIterator::Range { range, position } => {
+                let value = range.get(*position)?;
+                *position += 1;
+
+                Some((Object::integer(*position as i64 - 1), Object::integer(value)))
+            },
+        }
+    }
+}
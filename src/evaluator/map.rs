@@ -0,0 +1,126 @@
+use std::{collections::HashMap, fmt::Display};
+
+use super::{bigint::BigInt, object::{Object, ObjectType}};
+
+/// Value of an object that can be used as a map key, keys are compared by value rather than identity
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Null,
+    Integer(i64),
+    /// An integer too large for an `i64`, integers that fit never use this
+    BigInteger(BigInt),
+    Boolean(bool),
+    String(String),
+}
+
+impl MapKey {
+    /// Types whose objects can be used as keys
+    pub const TYPES: [ObjectType; 4] = [ObjectType::String, ObjectType::Integer, ObjectType::Boolean, ObjectType::Null];
+
+    pub fn from_object(object: &Object) -> Option<Self> {
+        Some(match object.get_type() {
+            ObjectType::Null => MapKey::Null,
+            ObjectType::Integer => match object.as_integer() {
+                Some(value) => MapKey::Integer(value),
+                None => MapKey::BigInteger(object.as_big_integer()?),
+            },
+            ObjectType::Boolean => MapKey::Boolean(object.as_boolean()?),
+            ObjectType::String => MapKey::String(object.as_str()?.to_owned()),
+            _ => return None,
+        })
+    }
+
+    pub fn to_object(&self) -> Object {
+        match self {
+            MapKey::Null => Object::null(),
+            MapKey::Integer(value) => Object::integer(*value),
+            MapKey::BigInteger(value) => Object::big_integer(value.to_owned()),
+            MapKey::Boolean(value) => Object::boolean(*value),
+            MapKey::String(value) => Object::string(value),
+        }
+    }
+}
+
+impl Display for MapKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapKey::Null => write!(f, "null"),
+            MapKey::Integer(value) => write!(f, "{}", value),
+            MapKey::BigInteger(value) => write!(f, "{}", value),
+            MapKey::Boolean(value) => write!(f, "{}", value),
+            MapKey::String(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+/// Hash map which keeps its entries in the order their keys were first inserted
+#[derive(Debug, Default)]
+pub struct Map {
+    entries: Vec<(MapKey, Object)>,
+    positions: HashMap<MapKey, usize>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<&Object> {
+        self.positions.get(key).map(|position| &self.entries[*position].1)
+    }
+
+    pub fn contains_key(&self, key: &MapKey) -> bool {
+        self.positions.contains_key(key)
+    }
+
+    /// Sets the value of a key, a key which is already in the map keeps its place
+    pub fn insert(&mut self, key: MapKey, value: Object) {
+        match self.positions.get(&key) {
+            Some(position) => self.entries[*position].1 = value,
+            None => {
+                self.positions.insert(key.to_owned(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &MapKey) -> Option<Object> {
+        let position = self.positions.remove(key)?;
+        let (_, value) = self.entries.remove(position);
+
+        for (key, _) in &self.entries[position..] {
+            if let Some(position) = self.positions.get_mut(key) {
+                *position -= 1;
+            }
+        }
+
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(MapKey, Object)> {
+        self.entries.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &MapKey> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Object> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+}
+
+/// Maps are equal when they have the same keys with equal values, whatever their order
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(key, value)| other.get(key) == Some(value))
+    }
+}
@@ -1,24 +1,59 @@
-use crate::{error, errors::{DynamicError, EvaluatorError}, parser::ast::{ArithmeticOperator, Assignment, BinaryExpression, BlockStatement, Expression, ForStatement, FunctionCallExpression, FunctionDeclareExpression, Identifier, IfStatement, Literal, LogicalOperator, Node, Operator, ReturnStatement, UnaryExpression, WhileStatement}};
+use crate::{error, errors::{DynamicError, EvaluatorError}, lexer::token::Span, parser::ast::{ArithmeticOperator, Assignment, BinaryExpression, BlockStatement, Expression, ExpressionStatement, ForInStatement, ForStatement, FunctionCallExpression, FunctionDeclareExpression, Identifier, IfStatement, IndexAssignment, IndexExpression, Literal, LogicalOperator, Node, Operator, RangeExpression, ReturnStatement, SliceExpression, TemplateExpression, TemplatePart, UnaryExpression, WhileStatement}};
 
-use self::{environment::Environment, object::{Object, ObjectType}};
+use self::{environment::Environment, iterator::ObjectIterator, map::{Map, MapKey}, object::{Object, ObjectType}, range::Range, trace::{RuntimeError, StackFrame}};
 
+pub mod bigint;
 pub mod environment;
+pub mod gc;
+pub mod iterator;
+pub mod map;
 pub mod object;
+pub mod range;
+pub mod trace;
 pub mod yaipl_std;
 
 pub type EvaluatorResult<T> = Result<T, DynamicError>;
-pub type StatementResult<T> = EvaluatorResult<(T, bool)>;
+pub type StatementResult = EvaluatorResult<ControlFlow>;
+
+/// How evaluation continues after a statement
+#[derive(Debug, Clone)]
+pub enum ControlFlow {
+    /// Carry on with the next statement, the object being the value of the statement
+    Normal(Object),
+    Break(Span),
+    Continue(Span),
+    Return(Object),
+}
+
+impl ControlFlow {
+    /// Value of a statement used where an object is expected, like the value of an assignment
+    fn into_value(self) -> EvaluatorResult<Object> {
+        match self {
+            ControlFlow::Normal(object) | ControlFlow::Return(object) => Ok(object),
+            ControlFlow::Break(span) => error!(EvaluatorError::InvalidControlFlow { keyword: String::from("break"), span }),
+            ControlFlow::Continue(span) => error!(EvaluatorError::InvalidControlFlow { keyword: String::from("continue"), span }),
+        }
+    }
+}
 
 pub struct Evaluator<'a> {
     env: Environment<'a>,
     ast: &'a Vec<Node>,
+    call_stack: Vec<StackFrame>,
+    /// Environments of the callers of the functions being called, which are out of reach of `env`
+    callers: Vec<Environment<'a>>,
+    /// Objects held while evaluating something else, e.g. the left operand while evaluating the right one
+    roots: Vec<Object>,
 }
 
 impl<'a> Evaluator<'a> {
     pub fn with_env(ast: &'a Vec<Node>, env: Environment<'a>) -> Self {
         Self {
             env,
-            ast
+            ast,
+            call_stack: Vec::new(),
+            callers: Vec::new(),
+            roots: Vec::new(),
         }
     }
 
@@ -29,276 +64,613 @@ impl<'a> Evaluator<'a> {
         Self::with_env(ast, env)
     }
 
+    pub fn into_env(self) -> Environment<'a> {
+        self.env
+    }
+
     pub fn eval(&mut self) -> Result<Object, DynamicError> {
-        let mut result: (Object, bool) = (Object::void(), false);
+        let mut result = Object::void();
         
         for node in self.ast {
-            result = self.eval_statement(node)?;
-            if result.1 {
-                break;
+            match self.eval_statement(node).map_err(|err| self.trace(err))? {
+                ControlFlow::Normal(object) => result = object,
+                // A top level return ends the program
+                ControlFlow::Return(object) => return Ok(object),
+                flow => return flow.into_value().map_err(|err| self.trace(err)),
             }
         }
 
-        Ok(result.0)
+        Ok(result)
+    }
+
+    /// Attaches the current call stack to an error, unless it already has one
+    fn trace(&self, err: DynamicError) -> DynamicError {
+        if err.is::<RuntimeError>() {
+            return err;
+        }
+
+        RuntimeError::new(err, &self.call_stack).into()
     }
 
-    fn eval_statement(&mut self, node: &'a Node) -> StatementResult<Object> {
+    fn eval_statement(&mut self, node: &'a Node) -> StatementResult {
+        self.collect_garbage();
+
         match node {
-            Node::BlockStatement(block) => self.eval_block(block),
-            Node::BreakStatement(_) => Ok((Object::void(), true)),
-            Node::ContinueStatement(_) => Ok((Object::void(), true)),
-            Node::EmptyStatement(_) => Ok((Object::void(), false)),
-            Node::ExpressionStatement(expr) => Ok((self.eval_expression(&expr.0)?, false)),
+            Node::BlockStatement(block) => self.eval_scoped_block(block),
+            Node::BreakStatement(statement) => Ok(ControlFlow::Break(statement.0.to_owned())),
+            Node::ContinueStatement(statement) => Ok(ControlFlow::Continue(statement.0.to_owned())),
+            Node::EmptyStatement(_) => Ok(ControlFlow::Normal(Object::void())),
+            // A bare block passes `break`, `continue` and `return` on to the enclosing statement
+            Node::ExpressionStatement(ExpressionStatement(Expression::BlockExpr(block), _)) => self.eval_scoped_block(block),
+            Node::ExpressionStatement(expr) => Ok(ControlFlow::Normal(self.eval_expression(&expr.0)?)),
             Node::IfStatement(statement) => self.eval_if(statement),
             Node::ElseStatement(statement) => self.eval_block(&statement.0),
             Node::ReturnStatement(statement) => self.eval_return(statement),
             Node::WhileStatement(statement) => self.eval_while(statement),
             Node::ForStatement(statement) => self.eval_for(statement),
+            Node::ForInStatement(statement) => self.eval_for_in(statement),
             _ => error!(format!("Not implemented statement {:#?}", node))
         }
     }
 
-    fn eval_for(&mut self, statement: &'a ForStatement) -> StatementResult<Object> {
-        let ForStatement(setter, condition, assignment, body) = statement;
+    /// Frees unreachable objects if enough has been allocated, which is only done between statements
+    ///
+    /// Objects the evaluator is holding onto outside of the environment have to be in `roots` by then.
+    fn collect_garbage(&mut self) {
+        if gc::should_collect() {
+            gc::collect(&self.roots, self.callers.iter().chain(std::iter::once(&self.env)));
+        }
+    }
 
-        let setter = match setter {
-            Expression::AssignmentExpr(setter) => setter,
-            _ => error!(EvaluatorError::InvalidExpression { 
-                expected: String::from("AssignmentExpr")
-            })
-        };
+    /// Keeps an object from being collected until the roots are released, returning its place in the roots
+    fn hold(&mut self, object: &Object) -> usize {
+        self.roots.push(object.to_owned());
+        self.roots.len() - 1
+    }
 
-        let condition = match condition {
-            Expression::BinaryExpr(condition) => condition,
-            _ => error!(EvaluatorError::InvalidExpression { 
-                expected: String::from("BinaryExpr")
-            })
-        };
+    /// Lets the objects held since the roots had the given length be collected again
+    fn release(&mut self, length: usize) {
+        self.roots.truncate(length);
+    }
+
+    /// Evaluates something which holds onto objects, releasing them afterwards whether it succeeds or not
+    fn holding<T>(&mut self, eval: impl FnOnce(&mut Self) -> EvaluatorResult<T>) -> EvaluatorResult<T> {
+        let roots = self.roots.len();
+        let result = eval(self);
+        self.release(roots);
+
+        result
+    }
+
+    fn eval_for(&mut self, statement: &'a ForStatement) -> StatementResult {
+        let scope = self.new_scope();
+        let result = self.holding(|this| this.eval_for_loop(statement));
+        self.destroy_scope(scope);
+
+        result
+    }
+
+    fn eval_for_loop(&mut self, statement: &'a ForStatement) -> StatementResult {
+        let ForStatement(setter, condition, step, body, _) = statement;
+        // The value of the last iteration is held, as the condition and step may run code before it is returned
+        let result = self.hold(&Object::void());
+
+        self.eval_expression(setter)?;
+        while self.eval_boolean(condition)? {
+            match self.eval_block(body)? {
+                ControlFlow::Normal(object) => self.roots[result] = object,
+                ControlFlow::Break(_) => break,
+                ControlFlow::Continue(_) => {},
+                flow @ ControlFlow::Return(_) => return Ok(flow),
+            }
+
+            self.eval_expression(step)?;
+        }
+
+        Ok(ControlFlow::Normal(self.roots[result].to_owned()))
+    }
 
-        let assignment = match assignment {
-            Expression::AssignmentExpr(assignment) => assignment,
-            _ => error!(EvaluatorError::InvalidExpression { 
-                expected: String::from("AssignmentExpr")
+    fn eval_for_in(&mut self, statement: &'a ForInStatement) -> StatementResult {
+        self.holding(|this| this.eval_for_in_loop(statement))
+    }
+
+    fn eval_for_in_loop(&mut self, statement: &'a ForInStatement) -> StatementResult {
+        let ForInStatement(variables, iterable, body, _) = statement;
+        let object = self.eval_expression(iterable)?;
+        self.hold(&object);
+
+        let iterator = match ObjectIterator::new(&object) {
+            Some(iterator) => iterator,
+            None => error!(EvaluatorError::InvalidType {
+                expected: ObjectIterator::TYPES.to_vec(),
+                found: object.get_type(),
+                span: iterable.span().to_owned(),
             })
         };
 
-        let mut result = (Object::void(), false);
+        let result = self.hold(&Object::void());
+        let yields_keys = iterator.yields_keys();
 
-        let scope_size = self.new_scope();
-        self.eval_assignment_expression(setter)?;
-        while self.eval_binary_expression(condition)?.as_boolean().expect("Couldn't take as boolean") {
-            result = self.eval_block(body)?;
-            
-            if result.1 {
-                break;
+        for (key, value) in iterator {
+            // Every iteration has its own scope, so closures keep the item they were created with
+            let scope = self.new_scope();
+            match variables.as_slice() {
+                [item] => self.env.define(&item.0, if yields_keys { key } else { value }),
+                [key_name, item] => {
+                    self.env.define(&key_name.0, key);
+                    self.env.define(&item.0, value);
+                },
+                _ => {},
             }
 
-            self.eval_assignment_expression(assignment)?;
+            let flow = self.eval_block(body);
+            self.destroy_scope(scope);
+
+            match flow? {
+                ControlFlow::Normal(object) => self.roots[result] = object,
+                ControlFlow::Break(_) => break,
+                ControlFlow::Continue(_) => {},
+                flow @ ControlFlow::Return(_) => return Ok(flow),
+            }
         }
-        self.destroy_scope(scope_size);
 
-        Ok(result)
+        Ok(ControlFlow::Normal(self.roots[result].to_owned()))
+    }
+
+    fn eval_while(&mut self, statement: &'a WhileStatement) -> StatementResult {
+        let scope = self.new_scope();
+        let roots = self.roots.len();
+        let result = self.eval_while_loop(statement);
+        self.release(roots);
+        self.destroy_scope(scope);
+
+        result
     }
 
-    fn eval_while(&mut self, statement: &'a WhileStatement) -> StatementResult<Object> {
-        let WhileStatement(condition, block) = statement;
-        let mut result = (Object::void(), false);
+    fn eval_while_loop(&mut self, statement: &'a WhileStatement) -> StatementResult {
+        let WhileStatement(condition, block, _) = statement;
+        let result = self.hold(&Object::void());
 
-        let scope_size = self.new_scope();
-        while self.eval_expression(condition)?.as_boolean().expect("Couldn't take as boolean") {
-            result = self.eval_block(block)?;
-            if result.1 {
-                break;
+        while self.eval_boolean(condition)? {
+            match self.eval_block(block)? {
+                ControlFlow::Normal(object) => self.roots[result] = object,
+                ControlFlow::Break(_) => break,
+                ControlFlow::Continue(_) => continue,
+                flow @ ControlFlow::Return(_) => return Ok(flow),
             }
         }
-        self.destroy_scope(scope_size);
 
-        Ok(result)
+        Ok(ControlFlow::Normal(self.roots[result].to_owned()))
     }
 
-    fn eval_return(&mut self, statement: &'a ReturnStatement) -> StatementResult<Object> {
-        let ReturnStatement(expression) = statement;
+    fn eval_return(&mut self, statement: &'a ReturnStatement) -> StatementResult {
+        let ReturnStatement(expression, _) = statement;
         let mut result = Object::void();
 
         if let Some(expression) = expression {
             result = self.eval_expression(expression)?;
         }
 
-        Ok((result, true))
+        Ok(ControlFlow::Return(result))
     }
 
-    fn eval_if(&mut self, statement: &'a IfStatement) -> StatementResult<Object> {
-        let IfStatement(condition, block, elif) = statement;
+    fn eval_if(&mut self, statement: &'a IfStatement) -> StatementResult {
+        let IfStatement(condition, block, elif, _) = statement;
         let condition = self.eval_expression(condition)?;
 
         if condition.is(ObjectType::Boolean) {
-            let scope_size = self.new_scope();
+            let scope = self.new_scope();
 
             let result = if condition.as_boolean().expect("Couldn't take as boolean") {
                 self.eval_block(block)
             } else if let Some(elif) = elif {
-                self.eval_statement(&elif)
+                self.eval_statement(elif)
             } else {
-                Ok((Object::void(), false))
+                Ok(ControlFlow::Normal(Object::void()))
             };
 
-            self.destroy_scope(scope_size);
+            self.destroy_scope(scope);
             return result;
         };
 
-        Ok((Object::void(), false))
+        Ok(ControlFlow::Normal(Object::void()))
     }
 
     fn eval_expression(&mut self, expression: &'a Expression) -> EvaluatorResult<Object> {
         Ok(match expression {
             Expression::AssignmentExpr(expression) => self.eval_assignment_expression(expression)?,
             Expression::BinaryExpr(expression) => self.eval_binary_expression(expression)?,
-            Expression::BlockExpr(expression) => (self.eval_block(expression)?).0,
+            Expression::BlockExpr(expression) => self.eval_scoped_block(expression)?.into_value()?,
             Expression::FunctionCallExpr(expression) => self.eval_func_call_expression(expression)?,
             Expression::FunctionDeclareExpr(expression) => self.eval_func_declare_expression(expression)?,
-            Expression::GroupExpr(expression) => self.eval_expression(expression)?,
+            Expression::GroupExpr(expression) => self.eval_expression(&expression.0)?,
             Expression::IdentifierExpr(expression) => self.eval_identifier(expression)?,
             Expression::LiteralExpr(expression) => self.eval_literal(expression)?,
             Expression::UnaryExpr(expression) => self.eval_unary_expression(expression)?,
+            Expression::IndexExpr(expression) => self.eval_index_expression(expression)?,
+            Expression::SliceExpr(expression) => self.eval_slice_expression(expression)?,
+            Expression::IndexAssignmentExpr(expression) => self.eval_index_assignment(expression)?,
+            Expression::RangeExpr(expression) => self.eval_range_expression(expression)?,
+            Expression::TemplateExpr(expression) => self.eval_template_expression(expression)?,
         })
     }
 
-    fn new_scope(&self) -> usize {
-        self.env.size()
+    /// Enters a scope nested in the current one, returning the environment to restore when leaving it
+    fn new_scope(&mut self) -> Environment<'a> {
+        let scope = self.env.child();
+        std::mem::replace(&mut self.env, scope)
     }
 
-    fn destroy_scope(&mut self, size: usize) {
-        self.env.truncate(size);
+    fn destroy_scope(&mut self, previous: Environment<'a>) {
+        self.env = previous;
     }
 
     fn eval_func_call_expression(&mut self, expression: &'a FunctionCallExpression) -> EvaluatorResult<Object> {
-        let FunctionCallExpression(identifier, args) = expression;
-        let object = self.env.get(&identifier.0);
-
-        if let Some(object) = object {
-            let object = object.to_owned();
+        self.holding(|this| this.eval_call(expression))
+    }
 
-            let mut built_args: Vec<Object> = Vec::new();
-            for arg in args {
-                built_args.push(self.eval_expression(arg)?);
-            }
-            
-            let result = match object.get_type() {
-                ObjectType::Function => {
-                    let function = object.as_function().expect("Couldn't take as function");
-            
-                    let scope_size = self.new_scope();
-                    for (index, arg) in built_args.iter().enumerate() {
-                        let identifier = &function.1[index];
-                        self.env.set(&identifier.0, arg.to_owned());
-                    }
-                    
-                    let result = self.eval_block(&function.2)?;
-                    self.destroy_scope(scope_size);
-                    result
-                },
-                ObjectType::NativeFunction => {
-                    let function = object.as_native_function().expect("Couldn't take as natve function");
-                    
-                    ((function.2)(&mut self.env, built_args), false)
-                },
-                _ => error!(EvaluatorError::InvalidType { 
-                    expected: vec![ObjectType::Function, ObjectType::NativeFunction],
-                    found: object.get_type(),
-                })
-            };
+    fn eval_call(&mut self, expression: &'a FunctionCallExpression) -> EvaluatorResult<Object> {
+        let FunctionCallExpression(callee, args, span) = expression;
+        let object = self.eval_expression(callee)?;
+        self.hold(&object);
 
-            return Ok(result.0);
+        let mut built_args: Vec<Object> = Vec::new();
+        for arg in args {
+            let arg = self.eval_expression(arg)?;
+            self.hold(&arg);
+            built_args.push(arg);
         }
-        
-        error!(EvaluatorError::ObjectNotFound { name: identifier.0.to_owned() })
+
+        let result = match object.get_type() {
+            ObjectType::Function => {
+                let function = object.as_function().expect("Couldn't take as function");
+
+                // The body runs in the environment the function was declared in, not the caller's
+                let caller = std::mem::replace(&mut self.env, function.env.child());
+                self.callers.push(caller);
+                for (parameter, arg) in function.declaration.1.iter().zip(built_args) {
+                    self.env.define(&parameter.0, arg);
+                }
+
+                // Frames are named after what the call site used, falling back to the function's own name
+                let name = match callee.as_ref() {
+                    Expression::IdentifierExpr(identifier) => Some(&identifier.0),
+                    _ => function.declaration.0.as_ref().map(|identifier| &identifier.0),
+                };
+                self.call_stack.push(StackFrame::new(name.map_or("<anonymous>", String::as_str), span.to_owned()));
+                let result = self.eval_block(&function.declaration.2)
+                    .and_then(ControlFlow::into_value)
+                    .map_err(|err| self.trace(err));
+                self.call_stack.pop();
+                let caller = self.callers.pop().expect("Caller environment was just pushed");
+                self.destroy_scope(caller);
+                result?
+            },
+            ObjectType::NativeFunction => {
+                let function = object.as_native_function().expect("Couldn't take as natve function");
+
+                (function.2)(&mut self.env, built_args)
+            },
+            _ => error!(EvaluatorError::InvalidType {
+                expected: vec![ObjectType::Function, ObjectType::NativeFunction],
+                found: object.get_type(),
+                span: callee.span().to_owned(),
+            })
+        };
+
+        Ok(result)
     }
 
-    fn eval_block(&mut self, expression: &'a BlockStatement) -> StatementResult<Object> {
-        let mut result = (Object::void(), false);
+    fn eval_block(&mut self, expression: &'a BlockStatement) -> StatementResult {
+        let mut result = Object::void();
         
         for statement in &expression.0 {
-            result = self.eval_statement(statement)?;
-            if result.1 {
-                break;
+            match self.eval_statement(statement)? {
+                ControlFlow::Normal(object) => result = object,
+                flow => return Ok(flow),
             }
         }
 
-        Ok(result)
+        Ok(ControlFlow::Normal(result))
     }
 
-    fn eval_func_declare_expression(&mut self, expression: &'a FunctionDeclareExpression) -> EvaluatorResult<Object> {
-        let object = Object::function(expression);
-        self.env.set(&expression.0.0, object);
+    /// Evaluates a block in a scope of its own, for blocks which are not the body of a function, loop or `if`
+    fn eval_scoped_block(&mut self, block: &'a BlockStatement) -> StatementResult {
+        let scope = self.new_scope();
+        let result = self.eval_block(block);
+        self.destroy_scope(scope);
 
-        Ok(Object::void())
+        result
+    }
+
+    fn eval_func_declare_expression(&mut self, expression: &'a FunctionDeclareExpression) -> EvaluatorResult<Object> {
+        Ok(Object::function(expression, self.env.clone()))
     }
 
     fn eval_identifier(&self, expression: &Identifier) -> EvaluatorResult<Object> {
-        let Identifier(identifier) = expression;
+        let Identifier(identifier, span) = expression;
         
         match self.env.get(identifier) {
             Some(object) => Ok(object.to_owned()),
-            None => error!(EvaluatorError::ObjectNotFound { name: identifier.to_owned() })
+            None => error!(EvaluatorError::ObjectNotFound { name: identifier.to_owned(), span: span.to_owned() })
         }
     }
 
     fn eval_assignment_expression(&mut self, expression: &'a Assignment) -> EvaluatorResult<Object> {
-        let Assignment(identifier, literal) = expression;
+        let Assignment(identifier, literal, _) = expression;
 
-        let value = self.eval_statement(&literal)?.0;
+        let value = self.eval_statement(literal)?.into_value()?;
         self.env.set(&identifier.0, value);
         Ok(Object::void())
     }
 
-    fn eval_literal(&self, expression: &Literal) -> EvaluatorResult<Object> {
+    fn eval_index_expression(&mut self, expression: &'a IndexExpression) -> EvaluatorResult<Object> {
+        let IndexExpression(target, index, _) = expression;
+        let object = self.eval_collection(target)?;
+
+        if object.is(ObjectType::Map) {
+            let key = self.holding(|this| {
+                this.hold(&object);
+                this.eval_key(index)
+            })?;
+
+            // Only borrowed once the key is evaluated, which may change the map
+            let map = object.as_map().expect("Couldn't take as map");
+            return match map.get(&key) {
+                Some(value) => Ok(value.to_owned()),
+                None => error!(EvaluatorError::KeyNotFound { key: key.to_string(), span: index.span().to_owned() }),
+            };
+        }
+
+        let index_value = self.holding(|this| {
+            this.hold(&object);
+            this.eval_integer(index)
+        })?;
+
+        // The index may have changed the list's length, e.g. by popping from it
+        let values = object.as_list().expect("Couldn't take as list");
+        let position = Self::index_position(index_value, values.len(), index)?;
+
+        Ok(values[position].to_owned())
+    }
+
+    fn eval_slice_expression(&mut self, expression: &'a SliceExpression) -> EvaluatorResult<Object> {
+        let SliceExpression(target, from, to, _) = expression;
+
+        let list = self.eval_list(target)?;
+        let (from, to) = self.holding(|this| {
+            this.hold(&list);
+            Ok((this.eval_slice_bound(from.as_deref())?, this.eval_slice_bound(to.as_deref())?))
+        })?;
+
+        // The bounds are clamped to the list as it is after evaluating them
+        let values = list.as_list().expect("Couldn't take as list");
+        let from = Self::slice_position(from, 0, values.len());
+        let to = Self::slice_position(to, values.len(), values.len());
+
+        Ok(Object::list(values[from..to.max(from)].to_vec()))
+    }
+
+    fn eval_index_assignment(&mut self, expression: &'a IndexAssignment) -> EvaluatorResult<Object> {
+        let IndexAssignment(IndexExpression(target, index, _), value, _) = expression;
+        let object = self.eval_collection(target)?;
+
+        if object.is(ObjectType::Map) {
+            let (key, value) = self.holding(|this| {
+                this.hold(&object);
+                Ok((this.eval_key(index)?, this.eval_expression(value)?))
+            })?;
+            let size = object.heap_size();
+            object.as_map_mut().expect("Couldn't take as map").insert(key, value);
+            gc::resize(&object, size);
+
+            return Ok(Object::void());
+        }
+
+        let (index_value, value) = self.holding(|this| {
+            this.hold(&object);
+            Ok((this.eval_integer(index)?, this.eval_expression(value)?))
+        })?;
+
+        // The index and value may have changed the list's length, e.g. by popping from it
+        let values = object.as_list_mut().expect("Couldn't take as list");
+        let position = Self::index_position(index_value, values.len(), index)?;
+        values[position] = value;
+
+        Ok(Object::void())
+    }
+
+    /// Evaluates something that can be indexed, a list or a map
+    fn eval_collection(&mut self, expression: &'a Expression) -> EvaluatorResult<Object> {
+        let object = self.eval_expression(expression)?;
+
+        if !object.is(ObjectType::List) && !object.is(ObjectType::Map) {
+            error!(EvaluatorError::InvalidType {
+                expected: vec![ObjectType::List, ObjectType::Map],
+                found: object.get_type(),
+                span: expression.span().to_owned(),
+            });
+        }
+
+        Ok(object)
+    }
+
+    fn eval_key(&mut self, expression: &'a Expression) -> EvaluatorResult<MapKey> {
+        let object = self.eval_expression(expression)?;
+
+        match MapKey::from_object(&object) {
+            Some(key) => Ok(key),
+            None => error!(EvaluatorError::InvalidType {
+                expected: MapKey::TYPES.to_vec(),
+                found: object.get_type(),
+                span: expression.span().to_owned(),
+            })
+        }
+    }
+
+    fn eval_range_expression(&mut self, expression: &'a RangeExpression) -> EvaluatorResult<Object> {
+        let RangeExpression(start, end, inclusive, step, _) = expression;
+
+        let start = self.eval_integer(start)?;
+        let end = self.eval_integer(end)?;
+        let step = match step {
+            Some(step) => match self.eval_integer(step)? {
+                0 => error!(EvaluatorError::ZeroStep { span: step.span().to_owned() }),
+                value => value,
+            },
+            None => 1,
+        };
+
+        Ok(Object::range(Range::new(start, end, *inclusive, step)))
+    }
+
+    /// Builds the string of a template, turning the value of every interpolation into text like `print` does
+    fn eval_template_expression(&mut self, expression: &'a TemplateExpression) -> EvaluatorResult<Object> {
+        let mut builder = String::new();
+
+        for part in &expression.0 {
+            match part {
+                TemplatePart::Text(text) => builder.push_str(text),
+                TemplatePart::Expression(expression) => builder.push_str(&self.eval_expression(expression)?.to_string()),
+            }
+        }
+
+        Ok(Object::string(&builder))
+    }
+
+    fn eval_list(&mut self, expression: &'a Expression) -> EvaluatorResult<Object> {
+        let object = self.eval_expression(expression)?;
+
+        if !object.is(ObjectType::List) {
+            error!(EvaluatorError::InvalidType {
+                expected: vec![ObjectType::List],
+                found: object.get_type(),
+                span: expression.span().to_owned(),
+            });
+        }
+
+        Ok(object)
+    }
+
+    /// Evaluates an integer that has to fit in an `i64`, like an index or a range bound
+    fn eval_integer(&mut self, expression: &'a Expression) -> EvaluatorResult<i64> {
+        let object = self.eval_expression(expression)?;
+
+        match object.as_integer() {
+            Some(value) => Ok(value),
+            None if object.is(ObjectType::Integer) => error!(EvaluatorError::IntegerTooLarge { span: expression.span().to_owned() }),
+            None => error!(EvaluatorError::InvalidType {
+                expected: vec![ObjectType::Integer],
+                found: object.get_type(),
+                span: expression.span().to_owned(),
+            })
+        }
+    }
+
+    /// Position of the index of `expression` into a list of `length` elements, negative indices count from the end
+    fn index_position(index: i64, length: usize, expression: &Expression) -> EvaluatorResult<usize> {
+        let position = if index < 0 { index + length as i64 } else { index };
+
+        if position < 0 || position >= length as i64 {
+            error!(EvaluatorError::IndexOutOfBounds { index, length, span: expression.span().to_owned() });
+        }
+
+        Ok(position as usize)
+    }
+
+    fn eval_slice_bound(&mut self, expression: Option<&'a Expression>) -> EvaluatorResult<Option<i64>> {
+        expression.map(|expression| self.eval_integer(expression)).transpose()
+    }
+
+    /// Position of a slice bound, which is clamped to the list instead of being bounds-checked
+    fn slice_position(index: Option<i64>, default: usize, length: usize) -> usize {
+        let index = match index {
+            Some(index) => index,
+            None => return default,
+        };
+
+        let position = if index < 0 { index + length as i64 } else { index };
+        position.clamp(0, length as i64) as usize
+    }
+
+    fn eval_literal(&mut self, expression: &'a Literal) -> EvaluatorResult<Object> {
         Ok(match expression {
             Literal::Integer(num) => Object::integer(num.0),
             Literal::Boolean(bool) => Object::boolean(bool.0),
             Literal::Float(num) => Object::float(num.0),
             Literal::String(str) => Object::string(&str.0),
-            Literal::List(list) => Object::list(&list.0),
-            Literal::Null => Object::null(),
+            Literal::List(list) => Object::list(self.holding(|this| {
+                let mut values = Vec::with_capacity(list.0.len());
+                for element in &list.0 {
+                    let value = this.eval_expression(element)?;
+                    this.hold(&value);
+                    values.push(value);
+                }
+
+                Ok(values)
+            })?),
+            Literal::Map(literal) => Object::map(self.holding(|this| {
+                let mut map = Map::new();
+                for (key, value) in &literal.0 {
+                    let key = this.eval_key(key)?;
+                    let value = this.eval_expression(value)?;
+                    this.hold(&value);
+                    map.insert(key, value);
+                }
+
+                Ok(map)
+            })?),
+            Literal::Null(_) => Object::null(),
         })
     }
 
     fn eval_unary_expression(&mut self, expression: &'a UnaryExpression) -> EvaluatorResult<Object> {
-        let UnaryExpression(operator, expr) = expression;
+        let UnaryExpression(operator, expr, span) = expression;
 
         let object = self.eval_expression(expr)?;
-        if operator == &Operator::Logical(LogicalOperator::Not) {
-            if object.is(ObjectType::Boolean) {
-                return Ok(object);
-            }
+        if operator == &Operator::Logical(LogicalOperator::Not) && object.is(ObjectType::Boolean) {
+            return Ok(object);
         }
 
         if operator == &Operator::Arithmetic(ArithmeticOperator::Minus) {
             return Ok(match object.get_type() {
-                ObjectType::Integer => Object::integer(-object.as_integer().expect("Couldn't take as integer")),
-                ObjectType::Float => Object::float(-object.as_f32().expect("Couldn't take as float")),
+                ObjectType::Integer => match object.as_integer().and_then(i64::checked_neg) {
+                    Some(value) => Object::integer(value),
+                    None => Object::big_integer(-&object.as_big_integer().expect("Couldn't take as big integer")),
+                },
+                ObjectType::Float => Object::float(-object.as_f64().expect("Couldn't take as float")),
                 _ => error!(EvaluatorError::InvalidType { 
                     expected: vec![ObjectType::Integer, ObjectType::Float],
                     found: object.get_type(),
+                    span: span.to_owned(),
                 })
             });
         }
 
         error!(EvaluatorError::InvalidExpression { 
-            expected: String::from("UnaryExpression")
+            expected: String::from("UnaryExpression"),
+            span: span.to_owned(),
         })
     }
 
     fn eval_binary_expression(&mut self, expression: &'a BinaryExpression) -> EvaluatorResult<Object> {
-        let BinaryExpression(left, operator, right) = expression;
+        let BinaryExpression(left, operator, right, span) = expression;
+
+        if let Operator::Logical(operator @ (LogicalOperator::And | LogicalOperator::Or)) = operator {
+            return self.eval_short_circuit(operator, left, right);
+        }
 
         let lhs = self.eval_expression(left)?;
-        let rhs = self.eval_expression(right)?;
+        let rhs = self.holding(|this| {
+            this.hold(&lhs);
+            this.eval_expression(right)
+        })?;
+        let operands = ((lhs.get_type(), left.span().to_owned()), (rhs.get_type(), right.span().to_owned()));
 
         let result = match operator {
             Operator::Logical(op) => match op {
-                LogicalOperator::Or => lhs.or(rhs),
                 LogicalOperator::Not => error!("Not operator not implemented for binary expression"),
-                LogicalOperator::And => lhs.and(rhs),
+                LogicalOperator::And | LogicalOperator::Or => unreachable!("Short-circuiting operators are evaluated separately"),
                 LogicalOperator::Equal => lhs.equal(rhs),
                 LogicalOperator::NotEqual => lhs.not_equal(rhs),
                 LogicalOperator::GreaterThan => lhs.greater_than(rhs),
@@ -319,8 +691,478 @@ impl<'a> Evaluator<'a> {
 
         match result {
             Ok(object) => Ok(object),
-            Err(err) => return Err(err.into())
+            Err(object::Error::DivisionByZero) => error!(EvaluatorError::DivisionByZero { span: right.span().to_owned() }),
+            Err(object::Error::Overflow) => error!(EvaluatorError::IntegerTooLarge { span: right.span().to_owned() }),
+            Err(_) => error!(EvaluatorError::InvalidOperands {
+                operator: operator.to_owned(),
+                left: operands.0,
+                right: operands.1,
+                span: span.to_owned(),
+            })
+        }
+    }
+
+    /// Evaluates `&&` and `||`, only evaluating the right operand when the left one does not decide the result
+    fn eval_short_circuit(&mut self, operator: &LogicalOperator, left: &'a Expression, right: &'a Expression) -> EvaluatorResult<Object> {
+        let lhs = self.eval_boolean(left)?;
+
+        let decided = match operator {
+            LogicalOperator::And => !lhs,
+            _ => lhs,
+        };
+
+        if decided {
+            return Ok(Object::boolean(lhs));
+        }
+
+        Ok(Object::boolean(self.eval_boolean(right)?))
+    }
+
+    /// Evaluates an operand of `&&` and `||` or the condition of a loop, which have to be booleans
+    fn eval_boolean(&mut self, expression: &'a Expression) -> EvaluatorResult<bool> {
+        let object = self.eval_expression(expression)?;
+
+        match object.as_boolean() {
+            Some(value) => Ok(value),
+            None => error!(EvaluatorError::InvalidType {
+                expected: vec![ObjectType::Boolean],
+                found: object.get_type(),
+                span: expression.span().to_owned(),
+            })
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{errors::{DynamicError, EvaluatorError}, lexer::Lexer, parser::{ast::Node, Parser}};
+    use super::{trace::RuntimeError, Evaluator};
+
+    fn eval(source: &str) -> Result<String, DynamicError> {
+        let mut lexer = Lexer::from(source);
+        let Node::Program(ast) = Parser::from(lexer.tokenize()?).parse()? else {
+            panic!("AST is not a program node");
+        };
+
+        let mut evaluator = Evaluator::new(&ast);
+        Ok(evaluator.eval()?.to_string())
+    }
+
+    /// Runs a program, returning its result as text
+    fn run(source: &str) -> String {
+        eval(source).unwrap_or_else(|err| panic!("Could not run {:?}: {}", source, err))
+    }
+
+    /// Runs a program which fails at runtime, returning its error without the call stack
+    fn run_error(source: &str) -> EvaluatorError {
+        let err = match eval(source) {
+            Ok(result) => panic!("Ran {:?} into {}", source, result),
+            Err(err) => err,
+        };
+
+        let err = match err.downcast::<RuntimeError>() {
+            Ok(err) => err.error,
+            Err(err) => err,
+        };
+
+        err.downcast_ref::<EvaluatorError>().unwrap_or_else(|| panic!("Not an evaluator error: {}", err)).to_owned()
+    }
+
+    #[test]
+    fn continue_skips_the_rest_of_the_iteration() {
+        assert_eq!(run("
+            total = 0;
+            for i = 0; i < 10; i += 1 {
+                if i % 2 == 0 {
+                    continue;
+                }
+
+                total += i;
+            }
+
+            return total;
+        "), "25");
+
+        assert_eq!(run("
+            i = 0;
+            total = 0;
+            while i < 5 {
+                i += 1;
+                if i == 3 {
+                    continue;
+                }
+
+                total += i;
+            }
+
+            return total;
+        "), "12");
+    }
+
+    #[test]
+    fn break_leaves_the_innermost_loop_only() {
+        assert_eq!(run("
+            pairs = 0;
+            for i = 0; i < 3; i += 1 {
+                for j = 0; j < 10; j += 1 {
+                    if j == 2 {
+                        break;
+                    }
+
+                    pairs += 1;
+                }
+            }
+
+            return pairs;
+        "), "6");
+    }
+
+    #[test]
+    fn return_leaves_the_function_from_inside_loops() {
+        assert_eq!(run("
+            find = (target) {
+                i = 0;
+                while true {
+                    for j = 0; j < 5; j += 1 {
+                        if i * 5 + j == target {
+                            return [i, j];
+                        }
+                    }
+
+                    i += 1;
+                }
+            };
+
+            found = find(12);
+            return found;
+        "), "[2, 2]");
+    }
+
+    #[test]
+    fn return_at_the_top_level_ends_the_program() {
+        assert_eq!(run("
+            for i = 0; i < 10; i += 1 {
+                if i == 4 {
+                    return i;
+                }
+            }
+
+            return -1;
+        "), "4");
+    }
+
+    #[test]
+    fn rejects_loop_control_used_as_a_value() {
+        assert!(matches!(run_error("
+            while true {
+                x = {
+                    break;
+                };
+            }
+        "), EvaluatorError::InvalidControlFlow { keyword, .. } if keyword == "break"));
+    }
+
+    #[test]
+    fn closures_keep_their_own_environment() {
+        assert_eq!(run("
+            make_counter = () {
+                count = 0;
+                return () {
+                    count += 1;
+                    return count;
+                };
+            };
+
+            first = make_counter();
+            second = make_counter();
+            first();
+            first();
+            second();
+
+            return [first(), second()];
+        "), "[3, 2]");
+    }
+
+    #[test]
+    fn functions_run_in_the_scope_they_are_defined_in() {
+        assert_eq!(run("
+            name = \"defined\";
+            show = () {
+                return name;
+            };
+
+            call = (name) {
+                return show();
+            };
+
+            return call(\"caller\");
+        "), "defined");
+    }
+
+    #[test]
+    fn parameters_shadow_outer_names() {
+        assert_eq!(run("
+            x = 1;
+            f = (x) {
+                x = 5;
+                return x;
+            };
+
+            result = f(2);
+            return [x, result];
+        "), "[1, 5]");
+    }
+
+    #[test]
+    fn assignments_update_enclosing_bindings() {
+        assert_eq!(run("
+            total = 0;
+            add = (n) {
+                total += n;
+                return total;
+            };
+
+            add(2);
+            if true {
+                add(3);
+            }
+
+            return total;
+        "), "5");
+    }
+
+    #[test]
+    fn names_first_assigned_in_a_block_end_with_it() {
+        assert!(matches!(run_error("
+            {
+                inner = 1;
+            }
+
+            return inner;
+        "), EvaluatorError::ObjectNotFound { name, .. } if name == "inner"));
+
+        assert!(matches!(run_error("
+            value = {
+                inner = 3;
+                inner * 2;
+            };
+
+            return inner;
+        "), EvaluatorError::ObjectNotFound { name, .. } if name == "inner"));
+
+        assert_eq!(run("
+            value = {
+                inner = 3;
+                inner * 2;
+            };
+
+            return value;
+        "), "6");
+    }
+
+    #[test]
+    fn indexes_lists_from_either_end() {
+        assert_eq!(run("
+            items = [1, \"two\", 3.0];
+            return [items[0], items[1], items[-1], items[-3], len(items)];
+        "), "[1, \"two\", 3.0, 1, 3]");
+    }
+
+    #[test]
+    fn assigns_to_list_elements() {
+        assert_eq!(run("
+            items = [1, 2, 3];
+            items[1] = 20;
+            items[-1] += 10;
+            return items;
+        "), "[1, 20, 13]");
+    }
+
+    #[test]
+    fn shares_lists_between_variables() {
+        assert_eq!(run("
+            first = [1];
+            second = first;
+            push(second, 2);
+            second[0] = 0;
+            return first;
+        "), "[0, 2]");
+    }
+
+    #[test]
+    fn slices_lists_into_copies() {
+        assert_eq!(run("
+            items = [0, 1, 2, 3, 4];
+            return [items[1:3], items[:2], items[-2:], items[3:1], items[-10:10]];
+        "), "[[1, 2], [0, 1], [3, 4], [], [0, 1, 2, 3, 4]]");
+
+        assert_eq!(run("
+            items = [0, 1, 2];
+            copy = items[:];
+            copy[0] = 9;
+            return [items[0], copy[0]];
+        "), "[0, 9]");
+    }
+
+    #[test]
+    fn rejects_indices_outside_of_lists() {
+        assert!(matches!(run_error("items = [1, 2, 3];\nitems[3];"), EvaluatorError::IndexOutOfBounds { index: 3, length: 3, .. }));
+        assert!(matches!(run_error("items = [1, 2, 3];\nitems[-4];"), EvaluatorError::IndexOutOfBounds { index: -4, length: 3, .. }));
+        assert!(matches!(run_error("items = [];\nitems[0] = 1;"), EvaluatorError::IndexOutOfBounds { index: 0, length: 0, .. }));
+    }
+
+    #[test]
+    fn looks_lists_up_after_evaluating_the_index() {
+        // Popping in the index leaves two elements, so the last one is at index 1
+        assert_eq!(run("
+            items = [1, 2, 3];
+            return items[pop(items) - 2];
+        "), "2");
+
+        assert!(matches!(run_error("
+            items = [1, 2, 3];
+            items[pop(items) - 1] = 0;
+        "), EvaluatorError::IndexOutOfBounds { index: 2, length: 2, .. }));
+    }
+
+    #[test]
+    fn looks_up_map_literal_keys() {
+        assert_eq!(run("
+            config = {\"name\": \"yaipl\", version: 1, 404: \"not found\", true: \"yes\", null: 0};
+            return [config[\"name\"], config[\"version\"], config[404], config[true], config[null]];
+        "), "[\"yaipl\", 1, \"not found\", \"yes\", 0]");
+    }
+
+    #[test]
+    fn inserts_updates_and_removes_map_entries() {
+        assert_eq!(run("
+            counts = {\"a\": 1};
+            counts[\"b\"] = 2;
+            counts[\"a\"] += 10;
+            remove(counts, \"b\");
+            counts[\"c\"] = 3;
+            return [keys(counts), values(counts), has(counts, \"b\"), has(counts, \"c\")];
+        "), "[[\"a\", \"c\"], [11, 3], false, true]");
+    }
+
+    #[test]
+    fn treats_empty_braces_as_a_map() {
+        assert_eq!(run("
+            empty = {};
+            empty[1] = \"one\";
+            return [typeof(empty), keys(empty)];
+        "), "[\"map\", [1]]");
+    }
+
+    #[test]
+    fn compares_maps_by_their_entries() {
+        assert_eq!(run("
+            same = {\"a\": 1, \"b\": [2]} == {\"b\": [2], \"a\": 1};
+            different = {\"a\": 1} != {\"a\": 2};
+            missing = {\"a\": 1} == {\"a\": 1, \"b\": 2};
+            return [same, different, missing];
+        "), "[true, true, false]");
+    }
+
+    #[test]
+    fn rejects_missing_map_keys() {
+        assert!(matches!(run_error("config = {\"name\": 1};\nconfig[\"version\"];"), EvaluatorError::KeyNotFound { .. }));
+        assert!(matches!(run_error("config = {1: 1};\nconfig[\"1\"];"), EvaluatorError::KeyNotFound { .. }));
+    }
+
+    #[test]
+    fn iterates_over_lists_and_strings() {
+        assert_eq!(run("
+            out = \"\";
+            for item in [1, \"a\", 2.5] {
+                out += \"${item};\";
+            }
+
+            for i, char in \"hey\" {
+                out += \"${i}${char}\";
+            }
+
+            return out;
+        "), "1;a;2.5;0h1e2y");
+    }
+
+    #[test]
+    fn iterates_over_maps_in_insertion_order() {
+        assert_eq!(run("
+            point = {y: 2, x: 1};
+            point[\"z\"] = 3;
+
+            out = \"\";
+            for key in point {
+                out += key;
+            }
+
+            for key, value in point {
+                out += \" ${key}=${value}\";
+            }
+
+            return out;
+        "), "yxz y=2 x=1 z=3");
+    }
+
+    #[test]
+    fn iterates_over_ranges_with_steps() {
+        let range = |range: &str| run(&format!("
+            out = \"\";
+            for i in {} {{
+                out += \"${{i}} \";
+            }}
+
+            return out;
+        ", range));
+
+        assert_eq!(range("0..3"), "0 1 2 ");
+        assert_eq!(range("0..=3"), "0 1 2 3 ");
+        assert_eq!(range("0..=10 step 5"), "0 5 10 ");
+        assert_eq!(range("0..10 step 4"), "0 4 8 ");
+        assert_eq!(range("3..0 step -1"), "3 2 1 ");
+        assert_eq!(range("3..=0 step -2"), "3 1 ");
+        assert_eq!(range("3..0"), "");
+        assert_eq!(range("0..0"), "");
+    }
+
+    #[test]
+    fn reuses_ranges_kept_in_variables() {
+        assert_eq!(run("
+            numbers = 1..=3;
+            total = 0;
+            for i in numbers {
+                for j in numbers {
+                    total += i * j;
+                }
+            }
+
+            return total;
+        "), "36");
+    }
+
+    #[test]
+    fn controls_for_in_loops() {
+        assert_eq!(run("
+            out = [];
+            for i in 0..100 {
+                if i % 2 == 0 {
+                    continue;
+                }
+
+                if i > 7 {
+                    break;
+                }
+
+                push(out, i);
+            }
+
+            return out;
+        "), "[1, 3, 5, 7]");
+    }
 
+    #[test]
+    fn rejects_zero_steps() {
+        assert!(matches!(run_error("for i in 0..3 step 0 {\n    i;\n}"), EvaluatorError::ZeroStep { .. }));
     }
 }
@@ -1,22 +1,28 @@
 use std::{alloc::{alloc, dealloc, Layout}, cmp::Ordering, fmt::Display, ptr::{addr_of_mut, drop_in_place}};
 
-use crate::parser::ast::{Expression, FunctionDeclareExpression};
+use crate::{parser::ast::FunctionDeclareExpression, utils::format_float};
 
-use super::environment::Environment;
+use super::{bigint::BigInt, environment::Environment, gc, map::{Map, MapKey}, range::Range};
 
-// largely based on https://github.com/dannyvankooten/nederlang/blob/tree-walker/src/object.rs
+/// Bits the result of an integer power may have, computing larger ones takes too long to be of any use
+const MAX_POWER_BITS: u64 = 1 << 18;
 
-pub const FUNCTION_PREFIX: &str = "__fc_";
+// largely based on https://github.com/dannyvankooten/nederlang/blob/tree-walker/src/object.rs
 
 #[derive(Clone, Debug)]
-pub struct Object(*mut u8);
-
-const TAG_MASK: usize = 0b111;
-const PTR_MASK: usize = !TAG_MASK;
-const VALUE_SHIFT_BITS: usize = 3;
+pub struct Object(Value);
 
-// TODO: Fix this. Tagged pointers cannot have more than 8 bits which is not enough for the amount of types we have
-// Possibly remove NativeFunction 
+/// What an object holds, small values are kept inline and everything else is allocated on the heap
+#[derive(Clone, Copy, Debug)]
+enum Value {
+    Null,
+    Void,
+    Integer(i64),
+    Boolean(bool),
+    NativeFunction(*const NativeFunctionObject<'static>),
+    /// Points to an allocation starting with a `Header`, which has the type of the value
+    Heap(*mut Header),
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ObjectType {
@@ -29,6 +35,8 @@ pub enum ObjectType {
     Function,
     NativeFunction,
     Void,
+    Map,
+    Range,
 }
 
 impl Display for ObjectType {
@@ -42,61 +50,82 @@ impl Display for ObjectType {
             ObjectType::List => f.write_str("list"),
             ObjectType::Function => f.write_str("function"),
             ObjectType::NativeFunction => f.write_str("nfunction"),
-            ObjectType::Void => f.write_str("void")
+            ObjectType::Void => f.write_str("void"),
+            ObjectType::Map => f.write_str("map"),
+            ObjectType::Range => f.write_str("range"),
         }
     }
 }
 
 #[derive(Clone, Debug)]
-pub struct NativeFunctionObject<'a>(pub &'a str, pub Vec<String>, pub fn(&mut Environment, Vec<Object>) -> Object);
+pub struct NativeFunctionObject<'a>(pub &'a str, pub &'a [&'a str], pub fn(&mut Environment, Vec<Object>) -> Object);
 
 impl<'a> Object {
-    fn from_type(pointer: *mut u8, object_type: ObjectType) -> Self {
-        Self((pointer as usize | object_type as usize & TAG_MASK) as _)
+    /// An object for a heap allocation, which has to start with a `Header`
+    fn from_heap(pointer: *mut u8) -> Self {
+        Self(Value::Heap(pointer as *mut Header))
     }
 
     pub fn null() -> Self {
-        Self::from_type(0 as _, ObjectType::Null)
+        Self(Value::Null)
     }
 
     pub fn void() -> Self {
-        Self::from_type(0 as _, ObjectType::Void)
+        Self(Value::Void)
     }
 
-    pub fn integer(value: i32) -> Self {
-        Self::from_type((value << VALUE_SHIFT_BITS) as _, ObjectType::Integer)
+    pub fn integer(value: i64) -> Self {
+        Self(Value::Integer(value))
+    }
+
+    /// An integer which is kept on the heap if it does not fit in an `i64`
+    pub fn big_integer(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(value) => Self::integer(value),
+            None => YaiplBigInt::from_big_int(value),
+        }
     }
     
     pub fn boolean(value: bool) -> Self {
-        match value {
-            false => Self::from_type(0 as _, ObjectType::Boolean),
-            true => Self::from_type((1 << VALUE_SHIFT_BITS) as _, ObjectType::Boolean)
-        }
+        Self(Value::Boolean(value))
+    }
+
+    pub fn list(values: Vec<Object>) -> Self {
+        YaiplList::from_vec(values)
     }
 
-    pub fn list(list: &'a Vec<Expression>) -> Self {
-        Self::from_type(list as *const Vec<Expression> as _, ObjectType::List)
+    pub fn map(map: Map) -> Self {
+        YaiplMap::from_map(map)
+    }
+
+    pub fn range(range: Range) -> Self {
+        YaiplRange::from_range(range)
     }
     
-    pub fn function(func: &'a FunctionDeclareExpression) -> Self {
-        Self::from_type(func as *const FunctionDeclareExpression as _, ObjectType::Function)
+    pub fn function(declaration: &'a FunctionDeclareExpression, env: Environment<'a>) -> Self {
+        YaiplFunction::from_declaration(declaration, env)
     }
 
-    pub fn native_function(func: &'a NativeFunctionObject) -> Self {
-        Self::from_type(func as *const NativeFunctionObject as _, ObjectType::NativeFunction)
+    pub fn native_function(func: &'static NativeFunctionObject<'static>) -> Self {
+        Self(Value::NativeFunction(func))
     }
 
     pub fn string(value: &'a str) -> Self {
         YaiplString::from_str(value)
     }
 
-    pub fn float(value: f32) -> Self {
-        YaiplFloat::from_f32(value)
+    pub fn float(value: f64) -> Self {
+        YaiplFloat::from_f64(value)
     }
 
     pub fn get_type(&self) -> ObjectType {
-        unsafe {
-            std::mem::transmute((self.0 as usize & TAG_MASK) as u8)
+        match self.0 {
+            Value::Null => ObjectType::Null,
+            Value::Void => ObjectType::Void,
+            Value::Integer(_) => ObjectType::Integer,
+            Value::Boolean(_) => ObjectType::Boolean,
+            Value::NativeFunction(_) => ObjectType::NativeFunction,
+            Value::Heap(_) => unsafe { Header::read(self) }.object_type.to_owned(),
         }
     }
 
@@ -105,43 +134,114 @@ impl<'a> Object {
     }
     
     pub fn as_boolean(&self) -> Option<bool> {
+        match self.0 {
+            Value::Boolean(value) => Some(value),
+            _ => None
+        }
+    }
+
+    /// The value of an integer that fits in an `i64`, which is every integer but the ones too large for it
+    pub fn as_integer(&self) -> Option<i64> {
+        match self.0 {
+            Value::Integer(value) => Some(value),
+            _ => None
+        }
+    }
+
+    /// The value of any integer, however large
+    pub fn as_big_integer(&self) -> Option<BigInt> {
+        match self.0 {
+            Value::Integer(value) => Some(BigInt::from(value)),
+            _ => match self.get_type() {
+                ObjectType::Integer => Some(unsafe { self.get::<YaiplBigInt>() }.value.to_owned()),
+                _ => None
+            }
+        }
+    }
+
+    /// An integer converted to a float, for arithmetic mixing the two
+    fn integer_as_f64(&self) -> Option<f64> {
+        match self.0 {
+            Value::Integer(value) => Some(value as f64),
+            _ => self.as_big_integer().map(|value| value.to_f64())
+        }
+    }
+
+    /// The value of an integer or float as a float, which is what integers are promoted to when mixed with floats
+    pub fn as_number(&self) -> Option<f64> {
+        match self.get_type() {
+            ObjectType::Integer => self.integer_as_f64(),
+            ObjectType::Float => self.as_f64(),
+            _ => None
+        }
+    }
+
+    /// Whether both objects are numbers but not of the same type, which are compared as floats
+    fn is_mixed_number(&self, other: &Self) -> bool {
+        matches!((self.get_type(), other.get_type()), (ObjectType::Integer, ObjectType::Float) | (ObjectType::Float, ObjectType::Integer))
+    }
+
+    fn integer_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.as_integer(), other.as_integer()) {
+            (Some(lhs), Some(rhs)) => lhs.partial_cmp(&rhs),
+            _ => self.as_big_integer()?.partial_cmp(&other.as_big_integer()?),
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&'a Vec<Object>> {
+        match self.get_type() {
+            ObjectType::List => Some(unsafe { &self.get::<YaiplList>().values }),
+            _ => None
+        }
+    }
+
+    /// The elements of a list, which are shared by every object pointing to it
+    pub fn as_list_mut(&self) -> Option<&'a mut Vec<Object>> {
         match self.get_type() {
-            ObjectType::Boolean => Some((self.0 as u8 >> VALUE_SHIFT_BITS) as u8 != 0),
+            ObjectType::List => Some(unsafe { &mut self.get_mut::<YaiplList>().values }),
             _ => None
         }
     }
 
-    pub fn as_integer(&self) -> Option<i32> {
+    pub fn as_map(&self) -> Option<&'a Map> {
         match self.get_type() {
-            ObjectType::Integer => Some(self.0 as i32 >> VALUE_SHIFT_BITS),
+            ObjectType::Map => Some(unsafe { &self.get::<YaiplMap>().map }),
             _ => None
         }
     }
 
-    pub fn as_list(&self) -> Option<&'a Vec<Expression>> {
+    /// The entries of a map, which are shared by every object pointing to it
+    pub fn as_map_mut(&self) -> Option<&'a mut Map> {
         match self.get_type() {
-            ObjectType::List => Some(unsafe { self.get::<Vec<Expression>>() }),
+            ObjectType::Map => Some(unsafe { &mut self.get_mut::<YaiplMap>().map }),
             _ => None
         }
     }
 
-    pub fn as_function(&self) -> Option<&'a FunctionDeclareExpression> {
+    pub fn as_range(&self) -> Option<&'a Range> {
         match self.get_type() {
-            ObjectType::Function => Some(unsafe { self.get::<FunctionDeclareExpression>() }),
+            ObjectType::Range => Some(unsafe { &self.get::<YaiplRange>().range }),
             _ => None
         }
     }
 
-    pub fn as_native_function(&self) -> Option<&'a NativeFunctionObject> {
+    pub fn as_function(&self) -> Option<&'a YaiplFunction<'a>> {
+        match self.get_type() {
+            ObjectType::Function => Some(unsafe { self.get::<YaiplFunction>() }),
+            _ => None
+        }
+    }
+
+    pub fn as_native_function(&self) -> Option<&'a NativeFunctionObject<'a>> {
         match self.get_type() {
             ObjectType::NativeFunction => Some(unsafe { self.get::<NativeFunctionObject>() } ),
             _ => None
         }
     }
 
-    pub fn as_f32(&self) -> Option<f32> {
+    pub fn as_f64(&self) -> Option<f64> {
         match self.get_type() {
-            ObjectType::Float => Some(unsafe { let result: f32 = YaiplFloat::read(self).value; result }),
+            ObjectType::Float => Some(unsafe { YaiplFloat::read(self).value }),
             _ => None
         }
     }
@@ -153,39 +253,95 @@ impl<'a> Object {
         }
     }
     
+    /// Pointer to the value of a heap allocated object or native function, null for other objects
     pub fn as_ptr(&self) -> *mut u8 {
-        (self.0 as usize & PTR_MASK) as *mut u8
+        match self.0 {
+            Value::Heap(pointer) => pointer as *mut u8,
+            Value::NativeFunction(pointer) => pointer as *mut u8,
+            _ => std::ptr::null_mut(),
+        }
     }
 
+    /// # Safety
+    /// The object must point to a live value of type `T`.
     pub unsafe fn get<T>(&self) -> &'a T {
         &*(self.as_ptr() as *const T)
     }
 
+    /// # Safety
+    /// The object must point to a live value of type `T` that is not aliased elsewhere.
+    #[allow(clippy::mut_from_ref)]
     pub unsafe fn get_mut<T>(&self) -> &'a mut T {
         &mut *(self.as_ptr() as *mut T)
     }
 
+    /// Whether the object points to a value allocated on the heap, which is owned by the garbage collector
+    pub fn is_heap(&self) -> bool {
+        matches!(self.0, Value::Heap(_))
+    }
+
+    /// Rough amount of bytes used by a heap allocated object, including what it owns outside of its allocation
+    pub fn heap_size(&self) -> usize {
+        use std::mem::size_of;
+
+        // Inline values have no heap allocation to read the size of
+        if !self.is_heap() {
+            return 0;
+        }
+
+        match self.get_type() {
+            ObjectType::Integer => size_of::<YaiplBigInt>() + std::mem::size_of_val(unsafe { self.get::<YaiplBigInt>() }.value.digits()),
+            ObjectType::Float => size_of::<YaiplFloat>(),
+            ObjectType::String => size_of::<YaiplString>() + unsafe { self.get::<YaiplString>() }.value.capacity(),
+            ObjectType::List => size_of::<YaiplList>() + unsafe { self.get::<YaiplList>() }.values.capacity() * size_of::<Object>(),
+            ObjectType::Map => size_of::<YaiplMap>() + unsafe { self.get::<YaiplMap>() }.map.len() * (size_of::<(MapKey, Object)>() + size_of::<(MapKey, usize)>()),
+            ObjectType::Range => size_of::<YaiplRange>(),
+            ObjectType::Function => size_of::<YaiplFunction>(),
+            _ => 0,
+        }
+    }
+
+    /// Frees a heap allocated object, which must not be used afterwards
     pub fn free(self) {
+        if !self.is_heap() {
+            return;
+        }
+
         unsafe {
             match self.get_type() {
+                ObjectType::Integer => YaiplBigInt::destroy(self),
                 ObjectType::Float => YaiplFloat::destroy(self),
                 ObjectType::String => YaiplString::destroy(self),
+                ObjectType::List => YaiplList::destroy(self),
+                ObjectType::Map => YaiplMap::destroy(self),
+                ObjectType::Range => YaiplRange::destroy(self),
+                ObjectType::Function => YaiplFunction::destroy(self),
                 _ => {}
             }
         }
     }
 
+    /// Like `to_string`, but with strings quoted so they can be told apart inside a list
+    pub fn to_repr(&self) -> String {
+        match self.as_str() {
+            Some(value) => format!("{:?}", value),
+            None => self.to_string(),
+        }
+    }
+
     pub fn to_string_with_type(&self) -> String {
         match self.get_type() {
-            ObjectType::Integer => format!("integer({})", self.as_integer().expect("Couldn't take as integer")),
+            ObjectType::Integer => format!("integer({})", self),
             ObjectType::Boolean => format!("boolean({})", self.as_boolean().expect("Couldn't take as boolean")),
-            ObjectType::Float => format!("float({})", self.as_f32().expect("Couldn't take as f32")),
+            ObjectType::Float => format!("float({})", self),
             ObjectType::String => format!("string(\"{}\")", self.as_str().expect("Couldn't take as str")),
             ObjectType::List => format!("list({})", self.as_list().expect("Couldn't take as list").len()),
-            ObjectType::Null => format!("null"),
-            ObjectType::Function => format!("function"),
-            ObjectType::NativeFunction => format!("nfunction"),
-            ObjectType::Void => format!("void")
+            ObjectType::Null => String::from("null"),
+            ObjectType::Function => String::from("function"),
+            ObjectType::NativeFunction => String::from("nfunction"),
+            ObjectType::Void => String::from("void"),
+            ObjectType::Map => format!("map({})", self.as_map().expect("Couldn't take as map").len()),
+            ObjectType::Range => format!("range({})", self.as_range().expect("Couldn't take as range")),
         }
     }
 }
@@ -194,21 +350,37 @@ impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.get_type() {
             ObjectType::Boolean => write!(f, "{}", self.as_boolean().expect("Couldn't take as boolean")),
-            ObjectType::Integer => write!(f, "{}", self.as_integer().expect("Couldn't take as integer")),
-            ObjectType::Float => write!(f, "{}", self.as_f32().expect("Couldn't take as f32")),
+            ObjectType::Integer => match self.as_integer() {
+                Some(value) => write!(f, "{}", value),
+                None => write!(f, "{}", self.as_big_integer().expect("Couldn't take as big integer")),
+            },
+            ObjectType::Float => write!(f, "{}", format_float(self.as_f64().expect("Couldn't take as f64"))),
             ObjectType::String => write!(f, "{}", self.as_str().expect("Couldn't take as str")),
-            ObjectType::List => write!(f, "[{}]", self.as_list().expect("Couldn't take as list").iter().map(|x| format!("{}", x)).collect::<Vec<String>>().join(", ")),
-            _ => write!(f, "{}", self.get_type().to_string())
+            ObjectType::List => write!(f, "[{}]", self.as_list().expect("Couldn't take as list").iter().map(|x| x.to_repr()).collect::<Vec<String>>().join(", ")),
+            ObjectType::Range => write!(f, "{}", self.as_range().expect("Couldn't take as range")),
+            ObjectType::Map => write!(f, "{{{}}}", self.as_map().expect("Couldn't take as map").iter().map(|(key, value)| format!("{}: {}", key, value.to_repr())).collect::<Vec<String>>().join(", ")),
+            _ => write!(f, "{}", self.get_type())
         }
     }
 }
 
 #[repr(C)]
 pub struct Header {
-    pub marked: bool
+    pub marked: bool,
+    pub object_type: ObjectType,
 }
 
 impl Header {
+    fn new(object_type: ObjectType) -> Self {
+        Self {
+            marked: false,
+            object_type,
+        }
+    }
+
+    /// # Safety
+    /// The object must point to a heap allocated value starting with a `Header`.
+    #[allow(clippy::mut_from_ref)]
     pub unsafe fn read(obj: &Object) -> &mut Self {
         obj.get_mut::<Self>()
     }
@@ -218,10 +390,36 @@ fn allocate(layout: Layout) -> *mut u8 {
     unsafe { alloc(layout) }
 }
 
+/// An integer too large to fit in an `i64`
+#[repr(C)]
+pub struct YaiplBigInt {
+    header: Header,
+    value: BigInt,
+}
+
+impl YaiplBigInt {
+    unsafe fn destroy(obj: Object) {
+        drop_in_place(obj.as_ptr() as *mut Self);
+        dealloc(obj.as_ptr(), Layout::new::<Self>());
+    }
+
+    fn from_big_int(value: BigInt) -> Object {
+        let ptr = Object::from_heap(allocate(Layout::new::<Self>()));
+        let obj = unsafe { ptr.get_mut::<Self>() };
+        unsafe {
+            addr_of_mut!(obj.header).write(Header::new(ObjectType::Integer));
+            addr_of_mut!(obj.value).write(value);
+        }
+
+        gc::track(&ptr);
+        ptr
+    }
+}
+
 #[repr(C)]
 pub struct YaiplFloat {
     header: Header,
-    value: f32,
+    value: f64,
 }
 
 impl YaiplFloat {
@@ -234,12 +432,13 @@ impl YaiplFloat {
         dealloc(obj.as_ptr(), Layout::new::<Self>());
     }
 
-    fn from_f32(value: f32) -> Object {
-        let ptr = Object::from_type(allocate(Layout::new::<Self>()), ObjectType::Float);
+    fn from_f64(value: f64) -> Object {
+        let ptr = Object::from_heap(allocate(Layout::new::<Self>()));
         let obj = unsafe { ptr.get_mut::<Self>() };
-        obj.header.marked = false;
+        unsafe { addr_of_mut!(obj.header).write(Header::new(ObjectType::Float)); }
         unsafe { addr_of_mut!(obj.value).write(value); }
 
+        gc::track(&ptr);
         ptr
     }
 }
@@ -257,27 +456,137 @@ impl YaiplString {
     }
 
     fn from_str(value: &str) -> Object {
-        let ptr = Object::from_type(allocate(Layout::new::<Self>()), ObjectType::String);
+        let ptr = Object::from_heap(allocate(Layout::new::<Self>()));
         let obj = unsafe { ptr.get_mut::<Self>() };
-        obj.header.marked = false;
+        unsafe { addr_of_mut!(obj.header).write(Header::new(ObjectType::String)); }
         unsafe { addr_of_mut!(obj.value).write(value.to_string()); }
 
+        gc::track(&ptr);
+        ptr
+    }
+}
+
+#[repr(C)]
+pub struct YaiplList {
+    header: Header,
+    values: Vec<Object>,
+}
+
+impl YaiplList {
+    unsafe fn destroy(obj: Object) {
+        drop_in_place(obj.as_ptr() as *mut Self);
+        dealloc(obj.as_ptr(), Layout::new::<Self>());
+    }
+
+    fn from_vec(values: Vec<Object>) -> Object {
+        let ptr = Object::from_heap(allocate(Layout::new::<Self>()));
+        let obj = unsafe { ptr.get_mut::<Self>() };
+        unsafe { addr_of_mut!(obj.header).write(Header::new(ObjectType::List)); }
+        unsafe { addr_of_mut!(obj.values).write(values); }
+
+        gc::track(&ptr);
+        ptr
+    }
+}
+
+#[repr(C)]
+pub struct YaiplMap {
+    header: Header,
+    map: Map,
+}
+
+impl YaiplMap {
+    unsafe fn destroy(obj: Object) {
+        drop_in_place(obj.as_ptr() as *mut Self);
+        dealloc(obj.as_ptr(), Layout::new::<Self>());
+    }
+
+    fn from_map(map: Map) -> Object {
+        let ptr = Object::from_heap(allocate(Layout::new::<Self>()));
+        let obj = unsafe { ptr.get_mut::<Self>() };
+        unsafe {
+            addr_of_mut!(obj.header).write(Header::new(ObjectType::Map));
+            addr_of_mut!(obj.map).write(map);
+        }
+
+        gc::track(&ptr);
+        ptr
+    }
+}
+
+#[repr(C)]
+pub struct YaiplRange {
+    header: Header,
+    range: Range,
+}
+
+impl YaiplRange {
+    unsafe fn destroy(obj: Object) {
+        drop_in_place(obj.as_ptr() as *mut Self);
+        dealloc(obj.as_ptr(), Layout::new::<Self>());
+    }
+
+    fn from_range(range: Range) -> Object {
+        let ptr = Object::from_heap(allocate(Layout::new::<Self>()));
+        let obj = unsafe { ptr.get_mut::<Self>() };
+        unsafe {
+            addr_of_mut!(obj.header).write(Header::new(ObjectType::Range));
+            addr_of_mut!(obj.range).write(range);
+        }
+
+        gc::track(&ptr);
+        ptr
+    }
+}
+
+/// A function declaration together with the environment it was declared in
+#[repr(C)]
+pub struct YaiplFunction<'a> {
+    header: Header,
+    pub declaration: &'a FunctionDeclareExpression,
+    pub env: Environment<'a>,
+}
+
+impl<'a> YaiplFunction<'a> {
+    unsafe fn destroy(obj: Object) {
+        drop_in_place(obj.as_ptr() as *mut Self);
+        dealloc(obj.as_ptr(), Layout::new::<Self>());
+    }
+
+    fn from_declaration(declaration: &'a FunctionDeclareExpression, env: Environment<'a>) -> Object {
+        let ptr = Object::from_heap(allocate(Layout::new::<Self>()));
+        let obj = unsafe { ptr.get_mut::<Self>() };
+        unsafe { addr_of_mut!(obj.header).write(Header::new(ObjectType::Function)); }
+        unsafe {
+            addr_of_mut!(obj.declaration).write(declaration);
+            addr_of_mut!(obj.env).write(env);
+        }
+
+        gc::track(&ptr);
         ptr
     }
 }
 
 impl PartialEq for Object {
     fn eq(&self, other: &Self) -> bool {
+        if self.is_mixed_number(other) {
+            return self.as_number() == other.as_number();
+        }
+
         if self.get_type() != other.get_type() {
             return false;
         }
 
         match self.get_type() {
-            ObjectType::Boolean | ObjectType::Integer | ObjectType::Void 
-            | ObjectType::Function | ObjectType::NativeFunction | ObjectType::Null
-            | ObjectType::List => self.0 == other.0,
-
-            ObjectType::Float => self.as_f32().expect("Couldn't take as f32") == other.as_f32().expect("Couldn't take as f32"),
+            ObjectType::Null | ObjectType::Void => true,
+            ObjectType::Boolean => self.as_boolean() == other.as_boolean(),
+            ObjectType::Integer => self.integer_cmp(other) == Some(Ordering::Equal),
+            ObjectType::Function | ObjectType::NativeFunction => self.as_ptr() == other.as_ptr(),
+
+            ObjectType::List => self.as_list().expect("Couldn't take as list") == other.as_list().expect("Couldn't take as list"),
+            ObjectType::Map => self.as_map().expect("Couldn't take as map") == other.as_map().expect("Couldn't take as map"),
+            ObjectType::Range => self.as_range().expect("Couldn't take as range") == other.as_range().expect("Couldn't take as range"),
+            ObjectType::Float => self.as_f64().expect("Couldn't take as f64") == other.as_f64().expect("Couldn't take as f64"),
             ObjectType::String => self.as_str().expect("Couldn't take as str") == other.as_str().expect("Couldn't take as str")
         }
     }
@@ -286,15 +595,23 @@ impl PartialEq for Object {
 impl PartialOrd for Object {
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.is_mixed_number(other) {
+            return self.as_number()?.partial_cmp(&other.as_number()?);
+        }
+
         debug_assert_eq!(self.get_type(), other.get_type());
         match self.get_type() {
-            ObjectType::Boolean | ObjectType::Integer | ObjectType::Null => self.0.partial_cmp(&other.0),
-            ObjectType::Float => self.as_f32().expect("Couldn't take as f32").partial_cmp(&other.as_f32().expect("Couldn't take as f32")),
+            ObjectType::Null => Some(Ordering::Equal),
+            ObjectType::Boolean => self.as_boolean().partial_cmp(&other.as_boolean()),
+            ObjectType::Integer => self.integer_cmp(other),
+            ObjectType::Float => self.as_f64().expect("Couldn't take as f64").partial_cmp(&other.as_f64().expect("Couldn't take as f64")),
             ObjectType::String => self.as_str().expect("Couldn't take as string").partial_cmp(other.as_str().expect("Couldn't take as string")),
             ObjectType::List => self.as_list().expect("Couldn't take as list").len().partial_cmp(&other.as_list().expect("Couldn't take as list").len()),
             ObjectType::Function => None,
             ObjectType::NativeFunction => None,
             ObjectType::Void => None,
+            ObjectType::Map => None,
+            ObjectType::Range => None,
         }
     }
 }
@@ -302,6 +619,9 @@ impl PartialOrd for Object {
 #[derive(Debug, PartialEq)]
 pub enum Error {
     TypeError(String),
+    DivisionByZero,
+    /// The result would be too large to compute
+    Overflow,
 }
 
 impl std::error::Error for Error {}
@@ -309,27 +629,29 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Error::TypeError(msg) => write!(f, "{}", msg),
+            Error::DivisionByZero => write!(f, "Division by zero"),
+            Error::Overflow => write!(f, "Result is too large"),
         }
     }
 }
 
 macro_rules! impl_arithmetic {
-    ($func_name:ident, $op:tt) => {
-        impl_arithmetic!($func_name, $op, (lhs, rhs) => {});
+    ($func_name:ident, $op:tt, $checked:ident) => {
+        impl_arithmetic!($func_name, $op, $checked, (lhs, rhs) => {});
     };
 
-    ($func_name:ident, $op:tt, ($lhs:ident, $rhs:ident) => { $($pat:pat => $result:expr),* }) => {
+    ($func_name:ident, $op:tt, $checked:ident, ($lhs:ident, $rhs:ident) => { $($pat:pat $(if $guard:expr)? => $result:expr),* }) => {
         pub fn $func_name(self, rhs: Self) -> Result<Object, Error> {
             
             let ($lhs, $rhs) = (self, rhs);
             
             let result = match ($lhs.get_type(), $rhs.get_type()) {
-                (ObjectType::Integer, ObjectType::Integer) => Object::integer($lhs.as_integer().expect("Couldn't take as integer") $op $rhs.as_integer().expect("Couldn't take as integer")),
-                (ObjectType::Float, ObjectType::Float) => Object::float($lhs.as_f32().expect("Couldn't take as f32") $op $rhs.as_f32().expect("Couldn't take as f32")),
-                (ObjectType::Float, ObjectType::Integer) => Object::float($lhs.as_f32().expect("Couldn't take as f32") $op $rhs.as_integer().expect("Couldn't take as integer") as f32),
-                (ObjectType::Integer, ObjectType::Float) => Object::float($lhs.as_integer().expect("Couldn't take as integer") as f32 $op $rhs.as_f32().expect("Couldn't take as f32")),
-                $($pat => $result,)*
-                _ => return Err(Error::TypeError(format!("Operator '&g&*{}&-&r' cannot be used for types '&g&*{:?}&-&r' and '&g&*{:?}&-&r'", stringify!($op), $lhs.get_type(), $rhs.get_type()))),
+                $($pat $(if $guard)? => $result,)*
+                (ObjectType::Integer, ObjectType::Integer) => $lhs.integer_arithmetic(&$rhs, i64::$checked, |lhs, rhs| lhs $op rhs),
+                (ObjectType::Float, ObjectType::Float) => Object::float($lhs.as_f64().expect("Couldn't take as f64") $op $rhs.as_f64().expect("Couldn't take as f64")),
+                (ObjectType::Float, ObjectType::Integer) => Object::float($lhs.as_f64().expect("Couldn't take as f64") $op $rhs.integer_as_f64().expect("Couldn't take as integer")),
+                (ObjectType::Integer, ObjectType::Float) => Object::float($lhs.integer_as_f64().expect("Couldn't take as integer") $op $rhs.as_f64().expect("Couldn't take as f64")),
+                _ => return Err(Error::TypeError(format!("Operator '{}' cannot be used for types '{:?}' and '{:?}'", stringify!($op), $lhs.get_type(), $rhs.get_type()))),
             };
 
             Ok(result)
@@ -342,7 +664,7 @@ macro_rules! impl_logical {
         pub fn $func_name(self, rhs: Self) -> Result<Object, Error> {
             let result = match (self.get_type(), rhs.get_type()) {
                 (ObjectType::Boolean, ObjectType::Boolean) => Object::boolean(self.as_boolean().expect("Couldn't take as boolean") $op rhs.as_boolean().expect("Couldn't take as boolean")),
-                _ => return Err(Error::TypeError(format!("Operator '&g&*{}&-&r' cannot be used for types '&g&*{:?}&-&r' and '&g&*{:?}&-&r'", stringify!($op), self.get_type(), rhs.get_type())))
+                _ => return Err(Error::TypeError(format!("Operator '{}' cannot be used for types '{:?}' and '{:?}'", stringify!($op), self.get_type(), rhs.get_type())))
             };
             Ok(result)
         }
@@ -352,8 +674,8 @@ macro_rules! impl_logical {
 macro_rules! impl_comparison {
     ($func_name:ident, $op:tt) => {
         pub fn $func_name(self, rhs: Self) -> Result<Object, Error> {
-            if self.get_type() != rhs.get_type() {
-                return Err(Error::TypeError(format!("Operator '&g&*{}&-&r' cannot be used for types '&g&*{:?}&-&r' and '&g&*{:?}&-&r'", stringify!($op), self.get_type(), rhs.get_type())));
+            if self.get_type() != rhs.get_type() && !self.is_mixed_number(&rhs) {
+                return Err(Error::TypeError(format!("Operator '{}' cannot be used for types '{:?}' and '{:?}'", stringify!($op), self.get_type(), rhs.get_type())));
             }
 
             Ok(Object::boolean(self $op rhs))
@@ -361,23 +683,69 @@ macro_rules! impl_comparison {
     };
 }
 
+#[allow(clippy::should_implement_trait)]
 impl Object {
-    impl_arithmetic!(add, +, (lhs, rhs) => {
+    /// Applies an operator to two integers, falling back to big integers when the result does not fit in an `i64`
+    fn integer_arithmetic(&self, rhs: &Self, checked: fn(i64, i64) -> Option<i64>, operator: fn(&BigInt, &BigInt) -> BigInt) -> Object {
+        if let Some(result) = self.as_integer().zip(rhs.as_integer()).and_then(|(lhs, rhs)| checked(lhs, rhs)) {
+            return Object::integer(result);
+        }
+
+        let (lhs, rhs) = (self.as_big_integer().expect("Couldn't take as big integer"), rhs.as_big_integer().expect("Couldn't take as big integer"));
+        Object::big_integer(operator(&lhs, &rhs))
+    }
+
+    fn integer_power(&self, rhs: &Self) -> Result<Object, Error> {
+        let base = self.as_big_integer().expect("Couldn't take as big integer");
+        let exponent = rhs.as_big_integer().expect("Couldn't take as big integer");
+
+        // The result is a fraction
+        if exponent.is_negative() {
+            return Ok(Object::float(base.to_f64().powf(exponent.to_f64())));
+        }
+
+        let exponent = match exponent.to_i64().and_then(|exponent| u32::try_from(exponent).ok()) {
+            Some(exponent) => exponent,
+            None => return match base.to_i64() {
+                Some(value @ (0 | 1)) => Ok(Object::integer(value)),
+                Some(-1) => Ok(Object::integer(if exponent.digits()[0].is_multiple_of(2) { 1 } else { -1 })),
+                _ => Err(Error::Overflow),
+            }
+        };
+
+        if let Some(result) = self.as_integer().and_then(|base| base.checked_pow(exponent)) {
+            return Ok(Object::integer(result));
+        }
+
+        // The result has at least this many bits, so bases like 1 can still have any exponent
+        if base.bits().saturating_sub(1) * exponent as u64 > MAX_POWER_BITS {
+            return Err(Error::Overflow);
+        }
+
+        Ok(Object::big_integer(base.pow(exponent)))
+    }
+
+    impl_arithmetic!(add, +, checked_add, (lhs, rhs) => {
         (ObjectType::String, _) => Object::string(&(lhs.as_str().expect("Couldn't take as str").to_string() + rhs.to_string().as_str())),
         (_, ObjectType::String) => Object::string(&(lhs.to_string() + rhs.as_str().expect("Couldn't take as str")))
     });
 
-    impl_arithmetic!(subtract, -);
-    impl_arithmetic!(multiply, *);
-    impl_arithmetic!(divide, /);
-    impl_arithmetic!(modulo, %);
+    impl_arithmetic!(subtract, -, checked_sub);
+    impl_arithmetic!(multiply, *, checked_mul);
+    impl_arithmetic!(divide, /, checked_div, (lhs, rhs) => {
+        (ObjectType::Integer, ObjectType::Integer) if rhs.as_integer() == Some(0) => return Err(Error::DivisionByZero)
+    });
+    impl_arithmetic!(modulo, %, checked_rem, (lhs, rhs) => {
+        (ObjectType::Integer, ObjectType::Integer) if rhs.as_integer() == Some(0) => return Err(Error::DivisionByZero)
+    });
+
     pub fn power(self, rhs: Self) -> Result<Object, Error> {
         let result = match (self.get_type(), rhs.get_type()) {
-            (ObjectType::Integer, ObjectType::Integer) => Object::integer(self.as_integer().expect("Couldn't take as integer").pow(rhs.as_integer().expect("Couldn't take as integer") as u32)),
-            (ObjectType::Float, ObjectType::Float) => Object::float(self.as_f32().expect("Couldn't take as f32").powf(rhs.as_f32().expect("Couldn't take as f32"))),
-            (ObjectType::Float, ObjectType::Integer) => Object::float(self.as_f32().expect("Couldn't take as f32").powf(rhs.as_integer().expect("Couldn't take as integer") as f32)),
-            (ObjectType::Integer, ObjectType::Float) => Object::float((self.as_integer().expect("Couldn't take as integer") as f32).powf(rhs.as_f32().expect("Couldn't take as f32"))),
-            _ => return Err(Error::TypeError(format!("Operator '&g&*{}&-&r' cannot be used for types '&g&*{:?}&-&r' and '&g&*{:?}&-&r", "^", self.get_type(), rhs.get_type()))),
+            (ObjectType::Integer, ObjectType::Integer) => self.integer_power(&rhs)?,
+            (ObjectType::Float, ObjectType::Float) => Object::float(self.as_f64().expect("Couldn't take as f64").powf(rhs.as_f64().expect("Couldn't take as f64"))),
+            (ObjectType::Float, ObjectType::Integer) => Object::float(self.as_f64().expect("Couldn't take as f64").powf(rhs.integer_as_f64().expect("Couldn't take as integer"))),
+            (ObjectType::Integer, ObjectType::Float) => Object::float(self.integer_as_f64().expect("Couldn't take as integer").powf(rhs.as_f64().expect("Couldn't take as f64"))),
+            _ => return Err(Error::TypeError(format!("Operator '{}' cannot be used for types '{:?}' and '{:?}'", "^", self.get_type(), rhs.get_type()))),
         };
 
         Ok(result)
@@ -387,9 +755,48 @@ impl Object {
     impl_comparison!(greater_than_equal, >=);
     impl_comparison!(lesser_than, <);
     impl_comparison!(lesser_than_equal, <=);
-    impl_comparison!(equal, ==);
-    impl_comparison!(not_equal, !=);
+
+    /// Values of different types are never equal, except for integers and floats of the same value
+    pub fn equal(self, rhs: Self) -> Result<Object, Error> {
+        Ok(Object::boolean(self == rhs))
+    }
+
+    pub fn not_equal(self, rhs: Self) -> Result<Object, Error> {
+        Ok(Object::boolean(self != rhs))
+    }
 
     impl_logical!(and, &&);
     impl_logical!(or, ||);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, Object, MAX_POWER_BITS};
+
+    fn power(base: i64, exponent: i64) -> Result<String, Error> {
+        Object::integer(base).power(Object::integer(exponent)).map(|result| result.to_string())
+    }
+
+    #[test]
+    fn raises_integers_past_i64() {
+        assert_eq!(power(2, 62), Ok(String::from("4611686018427387904")));
+        assert_eq!(power(2, 100), Ok(String::from("1267650600228229401496703205376")));
+        assert_eq!(power(-3, 41), Ok(String::from("-36472996377170786403")));
+    }
+
+    #[test]
+    fn raises_small_bases_to_any_exponent() {
+        assert_eq!(power(1, 3_000_000), Ok(String::from("1")));
+        assert_eq!(power(-1, 3_000_001), Ok(String::from("-1")));
+        assert_eq!(power(0, i64::MAX), Ok(String::from("0")));
+    }
+
+    #[test]
+    fn rejects_results_too_large_to_compute() {
+        assert_eq!(power(2, 3_000_000), Err(Error::Overflow));
+        assert_eq!(power(2, MAX_POWER_BITS as i64 + 1), Err(Error::Overflow));
+        assert_eq!(power(1 << 20, 20_000), Err(Error::Overflow));
+        assert_eq!(power(2, i64::MAX), Err(Error::Overflow));
+        assert!(power(3, 10_000).is_ok());
+    }
+}
@@ -0,0 +1,63 @@
+use std::fmt::Display;
+
+/// Integers from `start` towards `end`, counting down when `step` is negative
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    pub start: i64,
+    pub end: i64,
+    pub inclusive: bool,
+    pub step: i64,
+}
+
+impl Range {
+    pub fn new(start: i64, end: i64, inclusive: bool, step: i64) -> Self {
+        debug_assert_ne!(step, 0, "A range cannot have a step of zero");
+
+        Self { start, end, inclusive, step }
+    }
+
+    /// Amount of integers in the range
+    pub fn len(&self) -> usize {
+        let (start, end, step) = (self.start as i128, self.end as i128, self.step as i128);
+
+        // The last value the range may reach, ignoring the step
+        let last = match (self.inclusive, step > 0) {
+            (true, _) => end,
+            (false, true) => end - 1,
+            (false, false) => end + 1,
+        };
+
+        let distance = if step > 0 { last - start } else { start - last };
+        if distance < 0 {
+            return 0;
+        }
+
+        (distance / step.abs() + 1) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The integer at a position of the range
+    pub fn get(&self, position: usize) -> Option<i64> {
+        if position >= self.len() {
+            return None;
+        }
+
+        Some((self.start as i128 + position as i128 * self.step as i128) as i64)
+    }
+}
+
+impl Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operator = if self.inclusive { "..=" } else { ".." };
+        write!(f, "{}{}{}", self.start, operator, self.end)?;
+
+        if self.step != 1 {
+            write!(f, " step {}", self.step)?;
+        }
+
+        Ok(())
+    }
+}
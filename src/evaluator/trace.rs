@@ -0,0 +1,47 @@
+use std::{error::Error, fmt::Display};
+
+use crate::{errors::DynamicError, lexer::token::Span};
+
+/// A function call in progress, with the span of the call expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFrame {
+    pub function: String,
+    pub call_site: Span,
+}
+
+impl StackFrame {
+    pub fn new(function: &str, call_site: Span) -> Self {
+        Self {
+            function: function.to_owned(),
+            call_site,
+        }
+    }
+}
+
+/// An error raised while evaluating, with the calls that led to it, most recent first
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub error: DynamicError,
+    pub trace: Vec<StackFrame>,
+}
+
+impl RuntimeError {
+    pub fn new(error: DynamicError, call_stack: &[StackFrame]) -> Self {
+        Self {
+            error,
+            trace: call_stack.iter().rev().cloned().collect(),
+        }
+    }
+}
+
+impl Error for RuntimeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
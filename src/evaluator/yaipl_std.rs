@@ -1,4 +1,17 @@
-use super::{environment::Environment, object::{NativeFunctionObject, Object}};
+use std::sync::OnceLock;
+
+use super::{environment::Environment, gc, map::{Map, MapKey}, object::{NativeFunctionObject, Object, ObjectType}};
+
+static SCRIPT_ARGS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Sets the arguments passed to the script, exposed through `argc` and `argv`
+pub fn set_args(args: Vec<String>) {
+    let _ = SCRIPT_ARGS.set(args);
+}
+
+fn script_args() -> &'static [String] {
+    SCRIPT_ARGS.get().map(|args| args.as_slice()).unwrap_or(&[])
+}
 
 pub fn initialize<'a>(env: &mut Environment<'a>) {
     macro_rules! function {
@@ -8,16 +21,12 @@ pub fn initialize<'a>(env: &mut Environment<'a>) {
 
         ($name:literal, [$($args:tt),*], ($env:tt, $arg_param:tt) => $body:block) => {
             {
-                let function = NativeFunctionObject(concat!("__fc_", $name), vec!($($args.to_string()),*), |$env, $arg_param| {
+                // Lives for the whole program, so every environment shares it instead of allocating its own
+                static FUNCTION: NativeFunctionObject<'static> = NativeFunctionObject($name, &[$($args),*], |$env, $arg_param| {
                     $body
                 });
 
-                let func_box = Box::new(function);
-                let func_ptr = Box::into_raw(func_box);
-
-                let object = Object::native_function(unsafe { &*func_ptr });
-
-                env.set(concat!("__fc_", $name), object);
+                env.set($name, Object::native_function(&FUNCTION));
             }
         };
     }
@@ -28,7 +37,7 @@ pub fn initialize<'a>(env: &mut Environment<'a>) {
     });
 
     function!("print", ["arg"], (args) => {
-        let value = if args.len() > 0 {
+        let value = if !args.is_empty() {
             args[0].to_string()
         } else {
             String::from("")
@@ -39,7 +48,7 @@ pub fn initialize<'a>(env: &mut Environment<'a>) {
     });
 
     function!("println", ["arg"], (args) => {
-        let value = if args.len() > 0 {
+        let value = if !args.is_empty() {
             args[0].to_string()
         } else {
             String::from("")
@@ -50,7 +59,7 @@ pub fn initialize<'a>(env: &mut Environment<'a>) {
     });
 
     function!("typeof", ["arg"], (args) => {
-        let value: String = if args.len() > 0 {
+        let value: String = if !args.is_empty() {
             args[0].get_type().to_string()
         } else {
             String::new()
@@ -59,8 +68,137 @@ pub fn initialize<'a>(env: &mut Environment<'a>) {
         Object::string(&value)
     });
 
+    function!("len", ["value"], (args) => {
+        let length = match args.first() {
+            Some(value) => match value.get_type() {
+                ObjectType::List => value.as_list().map_or(0, |list| list.len()),
+                ObjectType::Map => value.as_map().map_or(0, |map| map.len()),
+                ObjectType::Range => value.as_range().map_or(0, |range| range.len()),
+                ObjectType::String => value.as_str().map_or(0, |string| string.chars().count()),
+                _ => return Object::null(),
+            },
+            None => return Object::null(),
+        };
+
+        Object::integer(length as i64)
+    });
+
+    function!("push", ["list", "value"], (args) => {
+        if let (Some(object), Some(value)) = (args.first(), args.get(1)) {
+            if let Some(list) = object.as_list_mut() {
+                let size = object.heap_size();
+                list.push(value.to_owned());
+                gc::resize(object, size);
+            }
+        }
+
+        Object::void()
+    });
+
+    function!("pop", ["list"], (args) => {
+        let Some(object) = args.first() else {
+            return Object::null();
+        };
+
+        let size = object.heap_size();
+        let popped = object.as_list_mut().and_then(|list| list.pop());
+        gc::resize(object, size);
+
+        popped.unwrap_or_else(Object::null)
+    });
+
+    function!("keys", ["map"], (args) => {
+        match args.first().and_then(|map| map.as_map()) {
+            Some(map) => Object::list(map.keys().map(MapKey::to_object).collect()),
+            None => Object::null()
+        }
+    });
+
+    function!("values", ["map"], (args) => {
+        match args.first().and_then(|map| map.as_map()) {
+            Some(map) => Object::list(map.values().cloned().collect()),
+            None => Object::null()
+        }
+    });
+
+    function!("has", ["map", "key"], (args) => {
+        let map = args.first().and_then(|map| map.as_map());
+        let key = args.get(1).and_then(MapKey::from_object);
+
+        match (map, key) {
+            (Some(map), Some(key)) => Object::boolean(map.contains_key(&key)),
+            _ => Object::boolean(false)
+        }
+    });
+
+    function!("remove", ["map", "key"], (args) => {
+        let object = args.first().filter(|map| map.is(ObjectType::Map));
+        let key = args.get(1).and_then(MapKey::from_object);
+
+        match (object, key) {
+            (Some(object), Some(key)) => {
+                let size = object.heap_size();
+                let removed = object.as_map_mut().expect("Couldn't take as map").remove(&key);
+                gc::resize(object, size);
+                removed.unwrap_or_else(Object::null)
+            },
+            _ => Object::null()
+        }
+    });
+
+    function!("help", ["function"], (args) => {
+        let text = match args.first() {
+            Some(value) => match (value.as_function(), value.as_native_function()) {
+                (Some(function), _) => format!(
+                    "{}\n\n{}",
+                    function.declaration.signature(),
+                    function.declaration.doc().unwrap_or("No documentation, it can be written in '##' comments before the declaration"),
+                ),
+                (_, Some(function)) => format!("{}({})\n\nBuilt-in function", function.0, function.1.join(", ")),
+                _ => format!("No documentation for values of type '{}'", value.get_type()),
+            },
+            None => String::from("Call help(function) to read what a function does, or enter ':help' for the commands of the REPL"),
+        };
+
+        println!("{}", text);
+        Object::void()
+    });
+
+    function!("argc", [], (_) => {
+        Object::integer(script_args().len() as i64)
+    });
+
+    function!("argv", ["index"], (args) => {
+        let arg = args.first()
+            .and_then(|index| index.as_integer())
+            .and_then(|index| script_args().get(index as usize));
+
+        match arg {
+            Some(arg) => Object::string(arg),
+            None => Object::null()
+        }
+    });
+
+    function!("gc_stats", [], (_) => {
+        let stats = gc::stats();
+        let mut map = Map::new();
+
+        for (name, value) in [
+            ("collections", stats.collections),
+            ("live_objects", stats.live_objects),
+            ("live_bytes", stats.live_bytes),
+            ("freed_objects", stats.freed_objects),
+            ("freed_bytes", stats.freed_bytes),
+            ("threshold", stats.threshold),
+        ] {
+            map.insert(MapKey::String(name.to_owned()), Object::integer(value as i64));
+        }
+
+        Object::map(map)
+    });
+
     function!("sleep", ["ms"], (args) => {
-        let ms = if args.len() > 0 {
+        let ms = if !args.is_empty() {
             args[0].to_string().parse::<u64>().unwrap()
         } else {
             0
@@ -1,25 +1,73 @@
 use crate::{error, errors::{DynamicError, LexerError}};
-use self::token::{Position, Token, TokenLiteral, TokenType, Tokens};
+use self::token::{FileId, Position, Span, TemplatePart, Token, TokenLiteral, TokenType, Tokens};
 
 pub mod token;
 
+pub const KEYWORDS: [(&str, TokenType); 14] = [
+    ("true", TokenType::Boolean),
+    ("false", TokenType::Boolean),
+    ("null", TokenType::Null),
+    ("inf", TokenType::Float),
+    ("nan", TokenType::Float),
+
+    ("if", TokenType::If),
+    ("elif", TokenType::ElIf),
+    ("else", TokenType::Else),
+    ("while", TokenType::While),
+    ("for", TokenType::For),
+    ("in", TokenType::In),
+    ("return", TokenType::Return),
+    ("break", TokenType::Break),
+    ("continue", TokenType::Continue),
+];
+
+/// Piece of a string literal as it is read, before the indentation of a multi-line string is removed
+enum StringPiece {
+    Part(TemplatePart),
+    /// Whitespace at the start of a line of a multi-line string
+    Indent(String),
+    Newline,
+}
+
 pub struct Lexer {
     pub tokens: Tokens,
+    /// Spans of the comments outside of strings, in order, which the formatter keeps
+    pub comments: Vec<Span>,
     chars: Vec<char>,
+    index: usize,
+    file: FileId,
     line: usize,
     col: usize,
+    offset: usize,
+    /// Lines of the `##` comments read since the last token, which are attached to the next one
+    doc: Option<String>,
 }
 
 impl Lexer {
     pub fn from(input: &str) -> Lexer {
+        Lexer::with_file(input, 0)
+    }
+
+    pub fn with_file(input: &str, file: FileId) -> Lexer {
         Lexer {
             tokens: Vec::new(),
+            comments: Vec::new(),
             chars: input.chars().collect::<Vec<char>>(),
+            index: 0,
+            file,
             line: 1,
-            col: 0
+            col: 1,
+            offset: 0,
+            doc: None,
         }
     }
 
+    pub fn keyword(word: &str) -> Option<TokenType> {
+        KEYWORDS.iter()
+            .find(|(keyword, _)| *keyword == word)
+            .map(|(_, token_type)| token_type.to_owned())
+    }
+
     pub fn tokens_to_string(tokens: &Tokens) -> String {
         let mut builder: String = String::new();
 
@@ -31,320 +79,835 @@ impl Lexer {
     }
 
     pub fn tokenize(&mut self) -> Result<&Tokens, DynamicError> {
-        while !self.chars.is_empty() {
-            let mut char = self.remove_char(0)?;
+        self.scan_tokens(false)?;
 
-            if char == '\n' {
-                continue;
+        if let Some(first) = self.tokens.first() {
+            if first.token_type == TokenType::EndOfLine {
+                self.tokens.remove(0);
             }
+        }
     
-            let matched_tokens: Tokens = match self.match_char(char) {
-                Some((token, len)) => vec![
-                    Token::from_pos(
-                        token, 
-                        self.get_pos(), 
-                        self.get_pos_offset(len as usize)
-                    )
-                ],
-    
-                None => {
-                    let mut ret: Tokens = Vec::new();
-                    let start: Position = self.get_pos();
-
-                    if char == '"' {
-                        let str = self.parse_string(&mut char)?;
-
-                        ret.push(Token::from_value_pos(
-                            TokenType::String, 
-                            start, 
-                            self.get_pos_offset(str.chars().count()),
-                            Some(TokenLiteral::String(str))
-                        ));
-                    } else {
-                        let mut word = self.parse_word(&mut char)?;
-                        
-                        if self.is_comment(&char) {
-                            while !self.chars.is_empty() && char != '\n' {
-                                char = self.remove_char(0)?;
-                            }
-                            
-                            self.tokens.push(Token::from_pos(
-                                TokenType::EndOfLine, 
-                                self.get_pos(), 
-                                self.get_pos_offset(1)
-                            ));
-                            continue;
-                        }
-                        
-                        if let Some((token, len)) = self.match_char(char) {
-                            ret.push(Token::from_pos(
-                                token, 
-                                self.get_pos(), 
-                                self.get_pos_offset(len as usize))
-                            );
-                        } else if char != ' ' && char != '\n' {
-                            word.push(char)
-                        }
-                        
-                        if word.is_empty() {
-                            continue;
-                        }
-
-                        let end: Position = self.get_pos();
-            
-                        ret.push(if let Ok(num) = word.replace("_", "").parse::<i32>() {
-                            Token::from_value_pos(
-                                TokenType::Integer, 
-                                start,
-                                self.get_pos_offset(num.to_string().chars().count()),
-                                Some(TokenLiteral::Integer(num))
-                            )
-                        } else if let Ok(num) = word.replace("_", "").parse::<f32>() {
-                            Token::from_value_pos(
-                                TokenType::Float, 
-                                start, 
-                                self.get_pos_offset(num.to_string().chars().count()),
-                                Some(TokenLiteral::Float(num))
-                            )
-                        } else {
-                            let (token_type, value) = match word.as_str() {
-                                "true" => (TokenType::Boolean, Some(TokenLiteral::Boolean(true))),
-                                "false" => (TokenType::Boolean, Some(TokenLiteral::Boolean(false))),
-                                "null" => (TokenType::Null, None),
-        
-                                // Keywords
-                                "if" => (TokenType::If, None),
-                                "elif" => (TokenType::ElIf, None),
-                                "else" => (TokenType::Else, None),
-                                "while" => (TokenType::While, None),
-                                "for" => (TokenType::For, None),
-                                "return" => (TokenType::Return, None),
-                                "break" => (TokenType::Break, None),
-                                "continue" => (TokenType::Continue, None),
-        
-                                _ => (TokenType::Symbol, Some(TokenLiteral::String(word)))
-                            };
-
-                            Token::from_value_pos(token_type, start, end, value)
-                        });
-                    };
-    
-                    ret
+        if let Some(last) = self.tokens.last() {
+            if last.token_type != TokenType::EndOfLine {
+                self.push_token(TokenType::EndOfLine, self.get_pos(), None);
+            }
+        }
+
+        self.push_token(TokenType::EndOfFile, self.get_pos(), None);
+
+        Ok(&self.tokens)
+    }
+
+    /// Scans tokens until the end of the input, or in an interpolation until the `}` closing it
+    fn scan_tokens(&mut self, interpolation: bool) -> Result<(), DynamicError> {
+        let mut depth = 0;
+
+        while let Some(char) = self.peek() {
+            let start = self.get_pos();
+
+            if char.is_whitespace() {
+                self.advance();
+                continue;
+            }
+
+            if self.is_comment(&char) {
+                if self.peek_next() == Some('[') {
+                    self.skip_block_comment()?;
+                    if !interpolation {
+                        self.comments.push(self.span_from(start));
+                    }
+
+                    continue;
                 }
-            };
-            
-            for token in matched_tokens.iter().rev() {
-                // Remove duplicate end of lines
-                if let Some(last) = self.tokens.last() {
-                    if last.token_type == TokenType::EndOfLine && token.token_type == TokenType::EndOfLine {
-                        continue;
+
+                let mut comment = String::new();
+                while let Some(char) = self.peek().filter(|char| *char != '\n') {
+                    comment.push(char);
+                    self.advance();
+                }
+
+                if !interpolation {
+                    self.comments.push(self.span_from(start.to_owned()));
+                }
+
+                // Doc comments are kept for the token after them
+                if let Some(line) = comment.strip_prefix("##") {
+                    let line = line.strip_prefix(' ').unwrap_or(line);
+                    match &mut self.doc {
+                        Some(doc) => {
+                            doc.push('\n');
+                            doc.push_str(line);
+                        },
+                        None => self.doc = Some(line.to_owned()),
                     }
                 }
-    
-                self.tokens.push(token.to_owned());
+
+                self.push_token(TokenType::EndOfLine, start, None);
+                continue;
             }
-        }
-    
-        if let Some(first) = self.tokens.first() {
-            match first.token_type {
-                TokenType::EndOfLine => {
-                    self.tokens.remove(0);
-                },
-                _ => {}
+
+            if matches!(char, '"' | '\'') || (char == 'r' && matches!(self.peek_next(), Some('"' | '\''))) {
+                let (token_type, value) = self.parse_string(char == 'r')?;
+                self.push_token(token_type, start, Some(value));
+                continue;
             }
+
+            if interpolation {
+                match char {
+                    '{' => depth += 1,
+                    '}' if depth == 0 => return Ok(()),
+                    '}' => depth -= 1,
+                    _ => {},
+                }
+            }
+
+            if let Some(token) = self.match_char(char) {
+                self.push_token(token, start, None);
+                continue;
+            }
+
+            if char.is_ascii_digit() || (char == '.' && self.peek_next().is_some_and(|char| char.is_ascii_digit())) {
+                let (token_type, value) = self.parse_number()?;
+                self.push_token(token_type, start, Some(value));
+                continue;
+            }
+
+            let word = self.parse_word()?;
+            let (token_type, value) = match Lexer::keyword(&word) {
+                Some(TokenType::Boolean) => (TokenType::Boolean, Some(TokenLiteral::Boolean(word == "true"))),
+                Some(TokenType::Float) => (TokenType::Float, Some(TokenLiteral::Float(if word == "inf" { f64::INFINITY } else { f64::NAN }))),
+                Some(token_type) => (token_type, None),
+                None => (TokenType::Symbol, Some(TokenLiteral::String(word)))
+            };
+
+            self.push_token(token_type, start, value);
         }
-    
+
+        Ok(())
+    }
+
+    /// Pushes a token ending at the current position
+    fn push_token(&mut self, token_type: TokenType, start: Position, value: Option<TokenLiteral>) {
+        // Remove duplicate end of lines
         if let Some(last) = self.tokens.last() {
-            match last.token_type {
-                TokenType::EndOfLine => {},
-                _ => {
-                    self.tokens.push(
-                        Token::from_pos(
-                            TokenType::EndOfLine, 
-                            self.get_pos(),
-                            self.get_pos_offset(1)
-                        )
-                    )
-                },
+            if last.token_type == TokenType::EndOfLine && token_type == TokenType::EndOfLine {
+                return;
             }
         }
 
-        self.tokens.push(
-            Token::from_pos(TokenType::EndOfFile, self.get_pos(), self.get_pos_offset(1))
-        );
+        let mut token = Token::from_value_pos(token_type, start, self.get_pos(), value);
+        token.file = self.file;
+        if token.token_type != TokenType::EndOfLine {
+            token.doc = self.doc.take();
+        }
 
-        Ok(&self.tokens)
+        self.tokens.push(token);
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
     }
 
-    fn remove_char(&mut self, index: usize) -> Result<char, DynamicError> {
-        if index > self.chars.len() {
-            error!(LexerError::OutOfBounds { index: index.to_string() })
+    fn peek_next(&self) -> Option<char> {
+        self.chars.get(self.index + 1).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let char = self.peek()?;
+        self.index += 1;
+        self.offset += char.len_utf8();
+
+        if char == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
         }
 
-        self.pos_advance(1);
-        let char = self.chars.remove(0);
-        
-        Ok(char)
+        Some(char)
     }
 
-    fn parse_word(&mut self, char: &mut char) -> Result<String, DynamicError> {
+    fn next_char(&mut self) -> Result<char, DynamicError> {
+        match self.advance() {
+            Some(char) => Ok(char),
+            None => error!(LexerError::OutOfBounds { index: self.index.to_string() })
+        }
+    }
+
+    fn is_word_char(&self, char: char) -> bool {
+        !char.is_whitespace() && !self.is_comment(&char) && !matches!(char, '"' | '\'') && !matches!(char,
+            ',' | '(' | '{' | '[' | ')' | '}' | ']' | '+' | '-' | '*' | '×' | '/' | '÷'
+            | '%' | '^' | '=' | '<' | '>' | '!' | ';' | ':'
+        )
+    }
+
+    fn parse_word(&mut self) -> Result<String, DynamicError> {
         let mut word = String::new();
 
-        while !self.chars.is_empty() && !char.is_whitespace() && !self.is_comment(&char) && self.match_char(char.to_owned()).is_none() {
-            word.push(char.to_owned());
-            *char = self.remove_char(0)?;
-        };
+        while let Some(char) = self.peek() {
+            if !self.is_word_char(char) || self.is_range_dot(char) {
+                break;
+            }
+
+            word.push(char);
+            self.advance();
+        }
+
+        if word.is_empty() {
+            let start = self.get_pos();
+            let character = self.next_char()?;
+            error!(LexerError::InvalidCharacter { character, span: self.span_from(start) })
+        }
 
         Ok(word)
     }
 
+    /// Whether the character is the first dot of a range like `0..10`, which ends words and numbers
+    fn is_range_dot(&self, char: char) -> bool {
+        char == '.' && self.peek_next() == Some('.')
+    }
+
+    /// Reads an integer or float literal, which starts with a digit or a '.' followed by one
+    ///
+    /// Integers can be written in hexadecimal (`0xFF`), binary (`0b1010`) or octal (`0o755`), and digits of
+    /// any number can be separated with underscores (`1_000.5e3`).
+    fn parse_number(&mut self) -> Result<(TokenType, TokenLiteral), DynamicError> {
+        let start = self.get_pos();
+
+        let radix = match (self.peek(), self.peek_next()) {
+            (Some('0'), Some('x' | 'X')) => Some((16, "hexadecimal")),
+            (Some('0'), Some('b' | 'B')) => Some((2, "binary")),
+            (Some('0'), Some('o' | 'O')) => Some((8, "octal")),
+            _ => None,
+        };
+
+        if let Some((radix, name)) = radix {
+            let prefix: String = [self.next_char()?, self.next_char()?].iter().collect();
+            let digits = self.take_digits(&start, radix)?;
+
+            if digits.is_empty() {
+                return self.invalid_number(start, format!("expected {} digits after '{}'", name, prefix));
+            }
+
+            self.expect_number_end(start.clone(), radix, name)?;
+            return match i64::from_str_radix(&digits, radix) {
+                Ok(value) => Ok((TokenType::Integer, TokenLiteral::Integer(value))),
+                Err(_) => error!(LexerError::IntegerTooLarge { span: self.span_from(start) }),
+            };
+        }
+
+        let mut literal = self.take_digits(&start, 10)?;
+        let mut is_float = false;
+
+        if self.peek() == Some('.') && !self.is_range_dot('.') {
+            self.advance();
+            literal.push('.');
+            literal.push_str(&self.take_digits(&start, 10)?);
+            is_float = true;
+        }
+
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let has_sign = matches!(self.peek_next(), Some('+' | '-'));
+            let first_digit = self.chars.get(self.index + 1 + has_sign as usize);
+
+            if !first_digit.is_some_and(char::is_ascii_digit) {
+                self.advance();
+                return self.invalid_number(start, String::from("expected digits after the exponent"));
+            }
+
+            self.advance();
+            literal.push('e');
+            if has_sign {
+                literal.push(self.next_char()?);
+            }
+
+            literal.push_str(&self.take_digits(&start, 10)?);
+            is_float = true;
+        }
+
+        self.expect_number_end(start.clone(), 10, "decimal")?;
+
+        if is_float {
+            return Ok((TokenType::Float, TokenLiteral::Float(literal.parse::<f64>()?)));
+        }
+
+        match literal.parse::<i64>() {
+            Ok(value) => Ok((TokenType::Integer, TokenLiteral::Integer(value))),
+            // Would otherwise have to be read as a float and lose precision
+            Err(_) => error!(LexerError::IntegerTooLarge { span: self.span_from(start) }),
+        }
+    }
+
+    /// Reads the digits of a number in the radix, leaving out the underscores separating them
+    ///
+    /// Every underscore has to be followed by a digit, so `1__0` and `1_` are invalid.
+    fn take_digits(&mut self, start: &Position, radix: u32) -> Result<String, DynamicError> {
+        let mut digits = String::new();
+
+        while let Some(char) = self.peek().filter(|char| char.is_digit(radix) || *char == '_') {
+            if char == '_' && !self.peek_next().is_some_and(|next| next.is_digit(radix)) {
+                self.advance();
+                return self.invalid_number(start.clone(), String::from("an underscore has to be followed by a digit"));
+            }
+
+            if char != '_' {
+                digits.push(char);
+            }
+
+            self.advance();
+        }
+
+        Ok(digits)
+    }
+
+    /// Makes sure a number is not directly followed by more of a word, like the last part of `1.2.3`
+    fn expect_number_end(&mut self, start: Position, radix: u32, name: &str) -> Result<(), DynamicError> {
+        let char = match self.peek() {
+            Some(char) if self.is_word_char(char) && !self.is_range_dot(char) => char,
+            _ => return Ok(()),
+        };
+
+        let reason = match char {
+            '.' if radix == 10 => String::from("a number can only have one decimal point"),
+            '.' => format!("{} numbers cannot have a fraction", name),
+            _ if char.is_ascii_alphanumeric() && radix != 10 => format!("'{}' is not a {} digit", char, name),
+            _ => format!("'{}' cannot be part of a number", char),
+        };
+
+        self.invalid_number(start, reason)
+    }
+
+    /// Reports a malformed number, spanning the rest of the word it is part of
+    fn invalid_number<T>(&mut self, start: Position, reason: String) -> Result<T, DynamicError> {
+        while self.peek().is_some_and(|char| self.is_word_char(char) && !self.is_range_dot(char)) {
+            self.advance();
+        }
+
+        error!(LexerError::InvalidNumber { reason, span: self.span_from(start) })
+    }
+
     fn is_comment(&self, char: &char) -> bool {
         char == &'#'
     }
 
-    fn parse_string(&mut self, char: &mut char) -> Result<String, DynamicError> {
+    /// Skips a `#[ ... ]#` comment, including the block comments nested in it
+    fn skip_block_comment(&mut self) -> Result<(), DynamicError> {
+        let mut openings = Vec::new();
+
+        loop {
+            let pos = self.get_pos();
+            match self.advance() {
+                Some('#') if self.accept_eq('[') => openings.push(pos),
+                Some(']') if self.accept_eq('#') => {
+                    openings.pop();
+                    if openings.is_empty() {
+                        return Ok(());
+                    }
+                },
+                Some(_) => {},
+                None => {
+                    let start = openings.pop().unwrap_or_default();
+                    let end = Position::new(start.line, start.col + 2, start.offset + 2);
+                    error!(LexerError::UnterminatedComment { span: Span::new(self.file, start, end) })
+                },
+            }
+        }
+    }
+
+    /// Reads a string literal, which is a template if it has interpolations like `${name}`
+    ///
+    /// Strings are quoted with `"` or `'`, and tripled quotes let a string span several lines, without the
+    /// indentation its lines have in common. Raw strings like `r"C:\path"` have no escapes or interpolations.
+    fn parse_string(&mut self, raw: bool) -> Result<(TokenType, TokenLiteral), DynamicError> {
+        let start = self.get_pos();
+        if raw {
+            self.next_char()?; // The `r` prefix
+        }
+
+        let quote = self.next_char()?;
+        let triple = self.peek() == Some(quote) && self.peek_next() == Some(quote);
+        if triple {
+            self.advance();
+            self.advance();
+        }
+
+        let opening = self.span_from(start);
         let mut builder = String::new();
-        *char = self.remove_char(0)?;
+        let mut pieces: Vec<StringPiece> = Vec::new();
+        // The text of a multi-line string starts on the line after the opening quotes
+        let mut line_start = triple && self.accept_eq('\n');
+
+        loop {
+            if line_start {
+                let mut indent = String::new();
+                while let Some(char) = self.peek().filter(|char| matches!(char, ' ' | '\t')) {
+                    indent.push(char);
+                    self.advance();
+                }
 
-        while !self.chars.is_empty() {
-            if !builder.ends_with("\\") && char == &'"' {
-                break;
+                pieces.push(StringPiece::Indent(indent));
+                line_start = false;
             }
 
-            if char == &'\\' {
-                *char = self.remove_char(0)?;
-                match char {
-                    'b' => builder.push('\u{0008}'),
-                    'f' => builder.push('\u{000C}'),
-                    'n' => builder.push('\n'),
-                    't' => builder.push('\t'),
-                    'r' => builder.push('\r'),
-                    '\'' => builder.push('\''),
-                    '\"' => builder.push('\"'),
-                    '\\' => builder.push('\\'),
-                    'u' => {
-                        let mut hex = String::new();
-                        
-                        for _ in 0..4 {
-                            hex.push(self.remove_char(0)?);
-                        }
-                        
-                        let unicode = match u32::from_str_radix(&hex, 16) {
-                            Ok(unicode) => unicode,
-                            Err(_) => error!(LexerError::InvalidCharacter { 
-                                character: char.to_owned(), 
-                                pos: self.get_pos()
-                            })
-                        };
-
-                        builder.push(std::char::from_u32(unicode).unwrap());
-                    },
-                    _ => builder.push(char.to_owned())
-                }
-                *char = self.remove_char(0)?;
+            let pos = self.get_pos();
+            let char = match self.advance() {
+                Some(char) if char == quote && !triple => break,
+                Some(char) if char == quote && self.peek() == Some(quote) && self.peek_next() == Some(quote) => {
+                    self.advance();
+                    self.advance();
+                    break;
+                },
+                Some(char) => char,
+                None => error!(LexerError::UnterminatedString { span: opening })
+            };
+
+            if triple && char == '\n' {
+                Self::flush_text(&mut builder, &mut pieces);
+                pieces.push(StringPiece::Newline);
+                line_start = true;
+                continue;
+            }
+
+            if raw {
+                builder.push(char);
+                continue;
+            }
+
+            if char == '$' && self.accept_eq('{') {
+                Self::flush_text(&mut builder, &mut pieces);
+                pieces.push(StringPiece::Part(TemplatePart::Tokens(self.parse_interpolation(pos)?)));
+                continue;
+            }
+
+            if char == '\\' {
+                builder.push(self.parse_escape(pos, &opening)?);
                 continue;
             }
 
-            builder.push(char.to_owned());
-            *char = self.remove_char(0)?;
+            builder.push(char);
         };
 
-        Ok(builder)
-    }
+        Self::flush_text(&mut builder, &mut pieces);
+        if triple {
+            pieces = Self::strip_indentation(pieces);
+        }
 
-    fn get_pos(&self) -> Position {
-        Position::from(self.line, self.col)
+        let mut parts: Vec<TemplatePart> = Vec::new();
+        for piece in pieces {
+            match piece {
+                StringPiece::Part(TemplatePart::Text(text)) | StringPiece::Indent(text) => builder.push_str(&text),
+                StringPiece::Newline => builder.push('\n'),
+                StringPiece::Part(part) => {
+                    if !builder.is_empty() {
+                        parts.push(TemplatePart::Text(std::mem::take(&mut builder)));
+                    }
+
+                    parts.push(part);
+                },
+            }
+        }
+
+        if parts.is_empty() {
+            return Ok((TokenType::String, TokenLiteral::String(builder)));
+        }
+
+        if !builder.is_empty() {
+            parts.push(TemplatePart::Text(builder));
+        }
+
+        Ok((TokenType::Template, TokenLiteral::Template(parts)))
     }
 
-    fn get_pos_offset(&self, amount: usize) -> Position {
-        let mut pos = self.get_pos();
-        pos.col += amount;
-        pos
+    fn flush_text(builder: &mut String, pieces: &mut Vec<StringPiece>) {
+        if !builder.is_empty() {
+            pieces.push(StringPiece::Part(TemplatePart::Text(std::mem::take(builder))));
+        }
     }
 
-    fn pos_advance(&mut self, amount: usize) {
-        for _ in 0..amount {
-            if self.chars.is_empty() {
-                break;
+    /// Removes the indentation every line of a multi-line string has in common, as well as the line of its
+    /// closing quotes, whose indentation counts too so it can be used to keep some of it
+    fn strip_indentation(pieces: Vec<StringPiece>) -> Vec<StringPiece> {
+        let mut lines: Vec<Vec<StringPiece>> = vec![Vec::new()];
+        for piece in pieces {
+            match piece {
+                StringPiece::Newline => lines.push(Vec::new()),
+                piece => lines.last_mut().expect("There is always a line").push(piece),
             }
-            
-            let char = self.chars.get(0).unwrap_or(&' ');
+        }
 
-            if char == &'\n' {
-                self.line += 1;
-                self.col = 0;
-            } else {
-                self.col += 1;
+        // Only whitespace before the closing quotes, which may be the only line when the string is empty
+        let closing = match lines.last().map(Vec::as_slice) {
+            Some([StringPiece::Indent(indent)]) => Some(indent.chars().count()),
+            _ => None,
+        };
+
+        if closing.is_some() {
+            lines.pop();
+        }
+
+        let common = lines.iter()
+            .filter_map(|line| match line.as_slice() {
+                [StringPiece::Indent(indent), _, ..] => Some(indent.chars().count()),
+                _ => None,
+            })
+            .chain(closing)
+            .min()
+            .unwrap_or(0);
+
+        let mut pieces = Vec::new();
+        for (index, line) in lines.into_iter().enumerate() {
+            if index > 0 {
+                pieces.push(StringPiece::Newline);
+            }
+
+            for piece in line {
+                pieces.push(match piece {
+                    StringPiece::Indent(indent) => StringPiece::Indent(indent.chars().skip(common).collect()),
+                    piece => piece,
+                });
             }
         }
+
+        pieces
     }
 
-    fn accept_eq(&mut self, char: char) -> bool {
-        if self.chars.len() < 2 {
-            return false;
+    /// Reads an escape sequence, `start` being the position of its backslash
+    fn parse_escape(&mut self, start: Position, opening: &Span) -> Result<char, DynamicError> {
+        let char = match self.advance() {
+            Some(char) => char,
+            None => error!(LexerError::UnterminatedString { span: opening.to_owned() })
+        };
+
+        Ok(match char {
+            'b' => '\u{0008}',
+            'f' => '\u{000C}',
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'u' => return self.parse_unicode_escape(start),
+            // Quotes, backslashes and dollar signs stand for themselves, like any other character
+            _ => char,
+        })
+    }
+
+    /// Reads the code point of a `\u{1F600}` escape, which has 1 to 6 hexadecimal digits, or of a `\u00E9` one
+    fn parse_unicode_escape(&mut self, start: Position) -> Result<char, DynamicError> {
+        let braced = self.accept_eq('{');
+        let mut hex = String::new();
+
+        while let Some(char) = self.peek().filter(|char| char.is_ascii_alphanumeric() && (braced || hex.len() < 4)) {
+            hex.push(char);
+            self.advance();
         }
-    
-        if let Some(next_char) = self.chars.get(0) {
-            if next_char.to_owned() == char {
-                self.chars.remove(0);
-                return true;
-            }
+
+        let reason = if let Some(char) = hex.chars().find(|char| !char.is_ascii_hexdigit()) {
+            Some(format!("'{}' is not a hexadecimal digit", char))
+        } else if !braced && hex.len() < 4 {
+            Some(String::from("expected 4 hexadecimal digits, or 1 to 6 of them in braces like '\\u{1F600}'"))
+        } else if braced && !(1..=6).contains(&hex.len()) {
+            Some(String::from("expected 1 to 6 hexadecimal digits"))
+        } else if braced && !self.accept_eq('}') {
+            Some(String::from("expected a '}' after the digits"))
+        } else {
+            None
+        };
+
+        if let Some(reason) = reason {
+            error!(LexerError::InvalidEscape { reason, span: self.span_from(start) })
+        }
+
+        let code = u32::from_str_radix(&hex, 16)?;
+        match char::from_u32(code) {
+            Some(char) => Ok(char),
+            None => error!(LexerError::InvalidEscape {
+                reason: match code {
+                    0xD800..=0xDFFF => format!("U+{:04X} is a surrogate, which is not a character on its own", code),
+                    _ => format!("U+{:X} is past the last character, U+10FFFF", code),
+                },
+                span: self.span_from(start),
+            })
+        }
+    }
+
+    /// Reads the tokens of an interpolation after its `${`, ending them with the closing `}` and the end of file
+    fn parse_interpolation(&mut self, start: Position) -> Result<Tokens, DynamicError> {
+        let opening = self.span_from(start);
+        let outer = std::mem::take(&mut self.tokens);
+        let result = self.scan_tokens(true);
+        let mut tokens = std::mem::replace(&mut self.tokens, outer);
+        result?;
+
+        let end = self.get_pos();
+        if !self.accept_eq('}') {
+            error!(LexerError::UnterminatedInterpolation { span: opening })
+        }
+
+        for (token_type, start) in [(TokenType::RightBrace, end), (TokenType::EndOfFile, self.get_pos())] {
+            let mut token = Token::from_pos(token_type, start, self.get_pos());
+            token.file = self.file;
+            tokens.push(token);
+        }
+
+        Ok(tokens)
+    }
+
+    fn get_pos(&self) -> Position {
+        Position::new(self.line, self.col, self.offset)
+    }
+
+    fn span_from(&self, start: Position) -> Span {
+        Span::new(self.file, start, self.get_pos())
+    }
+
+    fn accept_eq(&mut self, char: char) -> bool {
+        if self.peek() == Some(char) {
+            self.advance();
+            return true;
         }
     
         false
     }
     
-    fn match_char(&mut self, char: char) -> Option<(TokenType, u8)> {
-        macro_rules! accept_eq_ret {
-            ($sym_b:literal, $tru:expr, $fal:expr) => {
-                if self.accept_eq($sym_b) {
-                    return Some(($tru, 2));
-                } else {
-                    return Some(($fal, 1));
+    /// Consumes an operator or punctuation starting with `char`
+    fn match_char(&mut self, char: char) -> Option<TokenType> {
+        let next = self.chars.get(self.index + 1).copied();
+        let token = match char {
+            ',' => TokenType::Comma,
+            ':' => TokenType::Colon,
+            '(' => TokenType::LeftParen,
+            '{' => TokenType::LeftBrace,
+            '[' => TokenType::LeftBracket,
+            ')' => TokenType::RightParen,
+            '}' => TokenType::RightBrace,
+            ']' => TokenType::RightBracket,
+            ';' => TokenType::EndOfLine,
+            '.' if next == Some('.') => {
+                self.advance();
+                self.advance();
+
+                return Some(if self.accept_eq('=') { TokenType::RangeInclusive } else { TokenType::Range });
+            },
+            '&' if next == Some('&') => TokenType::And,
+            '|' if next == Some('|') => TokenType::Or,
+            '+' | '-' | '*' | '×' | '/' | '÷' | '%' | '^' | '=' | '<' | '>' | '!' => {
+                self.advance();
+
+                macro_rules! accept_eq_ret {
+                    ($sym_b:literal, $tru:expr, $fal:expr) => {
+                        if self.accept_eq($sym_b) { $tru } else { $fal }
+                    };
                 }
-            };
-        }
 
-        Some(match char {
-            ',' => (TokenType::Comma, 1),
-            '(' => (TokenType::LeftParen, 1),
-            '{' => (TokenType::LeftBrace, 1),
-            '[' => (TokenType::LeftBracket, 1),
-            ')' => (TokenType::RightParen, 1),
-            '}' => (TokenType::RightBrace, 1),
-            ']' => (TokenType::RightBracket, 1),
-    
-            '+' => accept_eq_ret!('=', TokenType::PlusAssign, TokenType::Plus),
-            '-' => accept_eq_ret!('=', TokenType::MinusAssign, TokenType::Minus),
-            '*' | '×' => accept_eq_ret!('=', TokenType::MultiplyAssign, TokenType::Multiply),
-            '/' | '÷' => accept_eq_ret!('=', TokenType::DivideAssign, TokenType::Divide),
-            '%' => accept_eq_ret!('=', TokenType::ModuloAssign, TokenType::Modulo),
-            '^' => accept_eq_ret!('=', TokenType::PowerAssign, TokenType::Power),
-            '=' => accept_eq_ret!('=', TokenType::Equal, TokenType::Assign),
-    
-            '<' => accept_eq_ret!('=', TokenType::LesserThanEqual, TokenType::LesserThan),
-            '>' => accept_eq_ret!('=', TokenType::GreaterThanEqual, TokenType::GreaterThan),
+                return Some(match char {
+                    '+' => accept_eq_ret!('=', TokenType::PlusAssign, TokenType::Plus),
+                    '-' => accept_eq_ret!('=', TokenType::MinusAssign, TokenType::Minus),
+                    '*' | '×' => accept_eq_ret!('=', TokenType::MultiplyAssign, TokenType::Multiply),
+                    '/' | '÷' => accept_eq_ret!('=', TokenType::DivideAssign, TokenType::Divide),
+                    '%' => accept_eq_ret!('=', TokenType::ModuloAssign, TokenType::Modulo),
+                    '^' => accept_eq_ret!('=', TokenType::PowerAssign, TokenType::Power),
+                    '=' => accept_eq_ret!('=', TokenType::Equal, TokenType::Assign),
             
-            '!' => accept_eq_ret!('=', TokenType::NotEqual, TokenType::Not),
-    
-            '&' => {
-                if self.accept_eq('&') {
-                    (TokenType::And, 2)
-                } else {
-                    return None
-                }
-            }
-    
-            '|' => {
-                if self.accept_eq('|') {
-                    (TokenType::Or, 2)
-                } else {
-                    return None
-                }
-            }
-    
-            ';' => (TokenType::EndOfLine, 1),
+                    '<' => accept_eq_ret!('=', TokenType::LesserThanEqual, TokenType::LesserThan),
+                    '>' => accept_eq_ret!('=', TokenType::GreaterThanEqual, TokenType::GreaterThan),
+                    _ => accept_eq_ret!('=', TokenType::NotEqual, TokenType::Not),
+                });
+            },
             _ => return None
-        })
+        };
+
+        // Two character tokens without an alternative
+        if matches!(token, TokenType::And | TokenType::Or) {
+            self.advance();
+        }
+
+        self.advance();
+        Some(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::LexerError;
+    use super::{token::{TemplatePart, TokenLiteral, TokenType}, Lexer};
+
+    /// Types and values of the tokens of a source, without the end of line and end of file closing it
+    fn lex(source: &str) -> Vec<(TokenType, Option<TokenLiteral>)> {
+        let mut lexer = Lexer::from(source);
+        let tokens = lexer.tokenize().unwrap_or_else(|err| panic!("Could not lex {:?}: {}", source, err));
+
+        tokens.iter()
+            .filter(|token| !matches!(token.token_type, TokenType::EndOfLine | TokenType::EndOfFile))
+            .map(|token| (token.token_type.to_owned(), token.value.to_owned()))
+            .collect()
+    }
+
+    fn lex_error(source: &str) -> LexerError {
+        let mut lexer = Lexer::from(source);
+        let err = match lexer.tokenize() {
+            Ok(tokens) => panic!("Lexed {:?} into {}", source, Lexer::tokens_to_string(tokens)),
+            Err(err) => err,
+        };
+
+        err.downcast_ref::<LexerError>().unwrap_or_else(|| panic!("Not a lexer error: {}", err)).to_owned()
+    }
+
+    fn integer(value: i64) -> Vec<(TokenType, Option<TokenLiteral>)> {
+        vec![(TokenType::Integer, Some(TokenLiteral::Integer(value)))]
+    }
+
+    fn float(value: f64) -> Vec<(TokenType, Option<TokenLiteral>)> {
+        vec![(TokenType::Float, Some(TokenLiteral::Float(value)))]
+    }
+
+    fn string(value: &str) -> Vec<(TokenType, Option<TokenLiteral>)> {
+        vec![(TokenType::String, Some(TokenLiteral::String(value.to_owned())))]
+    }
+
+    fn assert_invalid_number(source: &str, expected_reason: &str) {
+        match lex_error(source) {
+            LexerError::InvalidNumber { reason, .. } => assert_eq!(reason, expected_reason, "for {:?}", source),
+            err => panic!("Expected an invalid number for {:?}, found {:?}", source, err),
+        }
+    }
+
+    fn assert_invalid_escape(source: &str, expected_reason: &str) {
+        match lex_error(source) {
+            LexerError::InvalidEscape { reason, .. } => assert_eq!(reason, expected_reason, "for {:?}", source),
+            err => panic!("Expected an invalid escape for {:?}, found {:?}", source, err),
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn scans_radix_prefixes() {
+        assert_eq!(lex("0xFF"), integer(255));
+        assert_eq!(lex("0Xff"), integer(255));
+        assert_eq!(lex("0b1010"), integer(10));
+        assert_eq!(lex("0o755"), integer(493));
+        assert_eq!(lex("0x7FFF_FFFF_FFFF_FFFF"), integer(i64::MAX));
+    }
+
+    #[test]
+    fn scans_digit_separators() {
+        assert_eq!(lex("1_000_000"), integer(1_000_000));
+        assert_eq!(lex("0b1111_0000"), integer(240));
+        assert_eq!(lex("0x_ff"), integer(255));
+        assert_eq!(lex("1_000.000_5"), float(1000.0005));
+    }
+
+    #[test]
+    fn scans_floats_and_exponents() {
+        assert_eq!(lex("1.5"), float(1.5));
+        assert_eq!(lex(".5"), float(0.5));
+        assert_eq!(lex("1e3"), float(1000.0));
+        assert_eq!(lex("1.5E+2"), float(150.0));
+        assert_eq!(lex("2e-2"), float(0.02));
+        assert_eq!(lex("1_0e1_0"), float(1e11));
+    }
+
+    #[test]
+    fn leaves_range_dots_out_of_numbers() {
+        assert_eq!(lex("1..3"), vec![
+            (TokenType::Integer, Some(TokenLiteral::Integer(1))),
+            (TokenType::Range, None),
+            (TokenType::Integer, Some(TokenLiteral::Integer(3))),
+        ]);
+    }
+
+    #[test]
+    fn rejects_malformed_numbers() {
+        assert_invalid_number("0x", "expected hexadecimal digits after '0x'");
+        assert_invalid_number("0b", "expected binary digits after '0b'");
+        assert_invalid_number("1__0", "an underscore has to be followed by a digit");
+        assert_invalid_number("1_", "an underscore has to be followed by a digit");
+        assert_invalid_number("1e", "expected digits after the exponent");
+        assert_invalid_number("1e+", "expected digits after the exponent");
+        assert_invalid_number("0b102", "'2' is not a binary digit");
+        assert_invalid_number("0o8", "expected octal digits after '0o'");
+        assert_invalid_number("0x1.5", "hexadecimal numbers cannot have a fraction");
+        assert_invalid_number("1.2.3", "a number can only have one decimal point");
+        assert_invalid_number("12abc", "'a' cannot be part of a number");
+    }
+
+    #[test]
+    fn rejects_integers_too_large() {
+        assert!(matches!(lex_error("9223372036854775808"), LexerError::IntegerTooLarge { .. }));
+        assert!(matches!(lex_error("0x8000_0000_0000_0000"), LexerError::IntegerTooLarge { .. }));
+    }
+
+    #[test]
+    fn removes_common_indentation() {
+        assert_eq!(lex("\"\"\"\n    first\n      second\n    \"\"\""), string("first\n  second"));
+        assert_eq!(lex("'''\n\tone\n\ttwo\n\t'''"), string("one\ntwo"));
+    }
+
+    #[test]
+    fn ignores_blank_lines_for_indentation() {
+        assert_eq!(lex("\"\"\"\n    first\n\n    second\n    \"\"\""), string("first\n\nsecond"));
+    }
+
+    #[test]
+    fn counts_the_closing_quotes_indentation() {
+        // Deeper closing quotes take nothing more than the body has in common
+        assert_eq!(lex("\"\"\"\n  text\n      \"\"\""), string("text"));
+        // Shallower closing quotes keep some of the body's indentation
+        assert_eq!(lex("\"\"\"\n    text\n  \"\"\""), string("  text"));
+        // Closing quotes after text end the last line instead
+        assert_eq!(lex("\"\"\"\n    text\n    more\"\"\""), string("text\nmore"));
+    }
+
+    #[test]
+    fn scans_empty_strings() {
+        assert_eq!(lex("\"\""), string(""));
+        assert_eq!(lex("''"), string(""));
+        assert_eq!(lex("\"\"\"\n\"\"\""), string(""));
+        assert_eq!(lex("\"\"\"\n    \"\"\""), string(""));
+    }
+
+    #[test]
+    fn scans_escapes() {
+        assert_eq!(lex(r#""a\tb\n\"c\" \\ \${x}""#), string("a\tb\n\"c\" \\ ${x}"));
+        assert_eq!(lex(r#""\u00e9\u{1F600}\u{41}""#), string("é😀A"));
+    }
+
+    #[test]
+    fn rejects_invalid_unicode_escapes() {
+        assert_invalid_escape(r#""\u12""#, "expected 4 hexadecimal digits, or 1 to 6 of them in braces like '\\u{1F600}'");
+        assert_invalid_escape(r#""\u{}""#, "expected 1 to 6 hexadecimal digits");
+        assert_invalid_escape(r#""\u{1234567}""#, "expected 1 to 6 hexadecimal digits");
+        assert_invalid_escape(r#""\u{12G}""#, "'G' is not a hexadecimal digit");
+        assert_invalid_escape(r#""\u{41""#, "expected a '}' after the digits");
+    }
+
+    #[test]
+    fn rejects_out_of_range_unicode_escapes() {
+        assert_invalid_escape(r#""\u{110000}""#, "U+110000 is past the last character, U+10FFFF");
+        assert_invalid_escape(r#""\uD800""#, "U+D800 is a surrogate, which is not a character on its own");
+    }
+
+    #[test]
+    fn scans_raw_strings() {
+        assert_eq!(lex(r#"r"C:\raw\n""#), string(r"C:\raw\n"));
+        assert_eq!(lex(r#"r'${not} \u{41}'"#), string(r"${not} \u{41}"));
+        assert_eq!(lex("r\"\"\"\n    \\t\n    \"\"\""), string("\\t"));
+    }
+
+    #[test]
+    fn splits_templates_around_interpolations() {
+        let tokens = lex(r#""a${x}b""#);
+        let parts = match tokens.as_slice() {
+            [(TokenType::Template, Some(TokenLiteral::Template(parts)))] => parts,
+            tokens => panic!("Expected a template, found {:?}", tokens),
+        };
+
+        assert!(matches!(parts.as_slice(), [TemplatePart::Text(a), TemplatePart::Tokens(_), TemplatePart::Text(b)] if a == "a" && b == "b"));
+    }
+
+    #[test]
+    fn rejects_unterminated_strings() {
+        assert!(matches!(lex_error("\"abc"), LexerError::UnterminatedString { .. }));
+        assert!(matches!(lex_error("\"\"\"\nabc\n\""), LexerError::UnterminatedString { .. }));
+        assert!(matches!(lex_error("\"${x"), LexerError::UnterminatedInterpolation { .. }));
+        // The quote starts a string of its own inside the interpolation
+        assert!(matches!(lex_error("\"${x\""), LexerError::UnterminatedString { .. }));
+    }
+}
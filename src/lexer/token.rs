@@ -1,9 +1,12 @@
-use std::fmt::Display;
+use std::{fmt::Display, ops::Range};
 
-#[derive(Debug, Clone, PartialEq)]
+pub type FileId = usize;
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Position {
     pub line: usize,
     pub col: usize,
+    pub offset: usize,
 }
 
 impl Display for Position {
@@ -14,7 +17,11 @@ impl Display for Position {
 
 impl Position {
     pub fn from(line: usize, col: usize) -> Self {
-        Self { line, col }
+        Self::new(line, col, 0)
+    }
+
+    pub fn new(line: usize, col: usize, offset: usize) -> Self {
+        Self { line, col, offset }
     }
 
     pub fn to_tuple(&self) -> (usize, usize) {
@@ -22,12 +29,51 @@ impl Position {
     }
 }
 
+/// Region of a source file, from `start` up to but not including `end`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Span {
+    pub file: FileId,
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.start)
+    }
+}
+
+impl Span {
+    pub fn new(file: FileId, start: Position, end: Position) -> Self {
+        Self { file, start, end }
+    }
+
+    /// Byte range of the span in its source
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+
+    /// Span covering both this span and `other`
+    pub fn to(&self, other: &Span) -> Span {
+        Span::new(self.file, self.start.to_owned(), other.end.to_owned())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenLiteral {
-    Float(f32),
-    Integer(i32),
+    Float(f64),
+    Integer(i64),
     Boolean(bool),
     String(String),
+    Template(Vec<TemplatePart>),
+}
+
+/// Piece of a string with interpolations, either text or the tokens of an embedded expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplatePart {
+    Text(String),
+    /// Tokens of the expression, followed by the closing `}` and the end of file
+    Tokens(Tokens),
 }
 
 impl TokenLiteral {
@@ -37,6 +83,12 @@ impl TokenLiteral {
             TokenLiteral::Integer(value) => value.to_string(),
             TokenLiteral::Boolean(value) => value.to_string(),
             TokenLiteral::String(value) => value.to_string(),
+            TokenLiteral::Template(parts) => parts.iter()
+                .map(|part| match part {
+                    TemplatePart::Text(text) => text.to_owned(),
+                    TemplatePart::Tokens(_) => String::from("${...}"),
+                })
+                .collect(),
         }
     }
 }
@@ -44,18 +96,23 @@ impl TokenLiteral {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
+    pub file: FileId,
     pub start: Position,
     pub end: Position,
-    pub value: Option<TokenLiteral>
+    pub value: Option<TokenLiteral>,
+    /// Text of the `##` doc comments right before the token
+    pub doc: Option<String>,
 }
 
 impl Token {
     pub fn from_value_pos(token: TokenType, start: Position, end: Position, value: Option<TokenLiteral>) -> Self {
         Self {
             token_type: token,
+            file: 0,
             start,
             end,
-            value
+            value,
+            doc: None,
         }
     }
 
@@ -74,6 +131,10 @@ impl Token {
     pub fn pos_range(&self) -> (Position, Position) {
         (self.start.to_owned(), self.end.to_owned())
     }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.file, self.start.to_owned(), self.end.to_owned())
+    }
 }
 
 pub type Tokens = Vec<Token>;
@@ -85,6 +146,8 @@ pub enum TokenType {
     Float,
     Boolean,
     String,
+    /// String with interpolations, like `"Hello ${name}"`
+    Template,
     List,
     Null,
     
@@ -113,6 +176,8 @@ pub enum TokenType {
     GreaterThan,
     LesserThanEqual,
     GreaterThanEqual,
+    Range,
+    RangeInclusive,
     // EOF Operators
 
     // keywords
@@ -121,6 +186,7 @@ pub enum TokenType {
     Else,
     While,
     For,
+    In,
     Return,
     Break,
     Continue,
@@ -136,6 +202,23 @@ pub enum TokenType {
     EndOfFile,
     Symbol,
     Comma,
+    Colon,
 
     Unknown
+}
+
+impl TokenType {
+    pub fn is_operator(&self) -> bool {
+        matches!(self,
+            TokenType::Plus | TokenType::Minus | TokenType::Divide | TokenType::Multiply
+            | TokenType::Modulo | TokenType::Power
+            | TokenType::PlusAssign | TokenType::MinusAssign | TokenType::DivideAssign
+            | TokenType::MultiplyAssign | TokenType::ModuloAssign | TokenType::PowerAssign
+            | TokenType::Assign
+            | TokenType::Or | TokenType::And | TokenType::Not | TokenType::Equal | TokenType::NotEqual
+            | TokenType::LesserThan | TokenType::GreaterThan
+            | TokenType::LesserThanEqual | TokenType::GreaterThanEqual
+            | TokenType::Range | TokenType::RangeInclusive
+        )
+    }
 }
\ No newline at end of file
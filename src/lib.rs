@@ -2,4 +2,7 @@ pub mod lexer;
 pub mod parser;
 pub mod evaluator;
 pub mod utils;
-pub mod errors;
\ No newline at end of file
+pub mod errors;
+pub mod repl;
+pub mod cli;
+pub mod diagnostic;
@@ -1,6 +1,8 @@
-use std::{fs, io::{stdin, stdout, Write}, process::exit};
+use std::process::exit;
 
-use another_interpreted_language::{error, errors::DynamicError, evaluator::{object::{Object, ObjectType}, Evaluator}, lexer::{token::Tokens, Lexer}, parser::{ast::Node, Parser}, utils::colors::{BLUE, BOLD, CYAN, GREEN, MAGENTA, RED, RESET, UNDERLINE, YELLOW}};
+use another_interpreted_language::{cli::{Cli, Command, ErrorFormat, ExitStatus, Input}, diagnostic::{renderer::Renderer, Diagnostic, SourceMap}, error, errors::DynamicError, evaluator::{gc, object::ObjectType, yaipl_std, Evaluator}, lexer::{token::FileId, Lexer}, parser::{ast::{Node, Program}, docs, printer::Printer, Parser}, repl::{command::Command as ReplCommand, helper::ReplHelper, history_path, input::InputBuffer, Session}, utils::colors::{BLUE, BOLD, GREEN, RESET, UNDERLINE}};
+
+use rustyline::{error::ReadlineError, history::FileHistory, Editor};
 
 pub const NAME: &str = "YAIPL";
 pub const NAME_LONG: &str = "Yet Another Interpreted Programming Language";
@@ -8,95 +10,204 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
+    let program = args.first().map(|name| name.as_str()).unwrap_or("yaipl");
 
-    if args.len() < 2 {
-        // No input file provided, run REPL
-        repl();
-        exit(1);
-    }
-
-    // Input file provided
-    let file_path = &args[1];
-    let cwd = std::env::current_dir().expect("Could not get current directory");
-    let absolute_path = match fs::canonicalize(file_path) {
-        Ok(path) => path,
+    let cli = match Cli::parse(&args[1..]) {
+        Ok(cli) => cli,
         Err(err) => {
-            println!("Could not get absolute path: {}", err);
-            exit(1)
+            report(&err, &SourceMap::new(), ErrorFormat::from_args(&args[1..]));
+            exit(ExitStatus::from_error(&err).code());
         }
     };
 
-    let pretty_path = match absolute_path.strip_prefix(&cwd) {
-        Ok(path) => path.display().to_string(),
-        Err(_) => absolute_path.display().to_string()
+    let format = cli.error_format;
+    if let Some(threshold) = cli.gc_threshold {
+        gc::set_threshold(threshold);
+    }
+
+    let status = match cli.command {
+        Command::Repl => {
+            repl(format);
+            ExitStatus::Success
+        },
+        Command::Help => {
+            println!("{} - {} v{}\n\n{}", NAME, NAME_LONG, VERSION, Cli::usage(program));
+            ExitStatus::Success
+        },
+        Command::Version => {
+            println!("{} ({}) v{}", NAME, NAME_LONG, VERSION);
+            ExitStatus::Success
+        },
+        Command::Run { input, args } => {
+            yaipl_std::set_args(args);
+            run_program(&input, format, run)
+        },
+        Command::Check(input) => run_program(&input, format, |_| Ok(())),
+        Command::Fmt(input) => run_input(&input, format, |source, file| {
+            let mut lexer = Lexer::with_file(source, file);
+            let program = Parser::from(lexer.tokenize()?).parse()?;
+
+            print!("{}", Printer::format(&program, source, &lexer.comments));
+            Ok(())
+        }),
+        Command::Tokens(input) => run_input(&input, format, |source, file| {
+            let mut lexer = Lexer::with_file(source, file);
+            println!("{}", Lexer::tokens_to_string(lexer.tokenize()?));
+            Ok(())
+        }),
+        Command::Ast(input) => run_input(&input, format, |source, file| {
+            println!("{:#?}", parse(source, file)?);
+            Ok(())
+        }),
+        Command::Doc(input) => run_input(&input, format, |source, file| {
+            print!("{}", docs::generate(&parse(source, file)?));
+            Ok(())
+        }),
     };
 
-    if let Err(err) = parse_file(file_path) {
-        handle_errors(err, Some(pretty_path));
+    exit(status.code());
+}
+
+/// Reads the input and runs the action on it, reporting any error
+fn run_input(input: &Input, format: ErrorFormat, action: impl FnOnce(&str, FileId) -> Result<(), DynamicError>) -> ExitStatus {
+    run_source(input, format, |source, file, _| action(source, file))
+}
+
+/// Parses the input, reporting its warnings, and hands the program to the action if it has no syntax errors
+fn run_program(input: &Input, format: ErrorFormat, action: impl FnOnce(Program) -> Result<(), DynamicError>) -> ExitStatus {
+    run_source(input, format, |source, file, sources| {
+        let mut lexer = Lexer::with_file(source, file);
+        let output = Parser::from(lexer.tokenize()?).parse_recovering();
+
+        for warning in &output.warnings {
+            report(warning, sources, format);
+        }
+
+        action(output.into_program()?)
+    })
+}
+
+fn run_source(input: &Input, format: ErrorFormat, action: impl FnOnce(&str, FileId, &SourceMap) -> Result<(), DynamicError>) -> ExitStatus {
+    let mut sources = SourceMap::new();
+    let result = input.read().and_then(|source| {
+        let file = sources.add(input.name(), source.as_str());
+        action(&source, file, &sources)
+    });
+
+    match result {
+        Ok(()) => ExitStatus::Success,
+        Err(err) => {
+            report(&err, &sources, format);
+            ExitStatus::from_error(&err)
+        }
     }
 }
 
-pub fn repl() {
-    println!("{}{}{} - {}REPL Mode{}", 
-        format!("{}{}{}", GREEN, BOLD, UNDERLINE),
+pub fn repl(format: ErrorFormat) {
+    println!("{}{}{}{}{} - {}{}REPL Mode{}", 
+        GREEN, BOLD, UNDERLINE,
         NAME,
         RESET,
-        format!("{}{}", BLUE, BOLD), 
+        BLUE, BOLD,
         RESET
     );
     
-    let stdin = stdin();
-    let mut buf = String::new();
+    let mut editor: Editor<ReplHelper, FileHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            println!("Could not create line editor: {}", err);
+            exit(1)
+        }
+    };
 
-    loop {
-        print!("\n{}{}>>>{} ", BOLD, BLUE, RESET);
-        let _ = stdout().flush();
-        let _ = stdin.read_line(&mut buf);
+    let history = history_path();
+    if let Some(path) = &history {
+        let _ = editor.load_history(path); // The file does not exist on first run
+    }
 
-        if !buf.ends_with(';') {
-            buf.push(';');
-        }
+    let mut session = Session::new();
+    let mut helper = ReplHelper::new();
+    helper.update_names(session.env());
+    editor.set_helper(Some(helper));
 
-        let (_, _, result) = match interpret(buf.to_owned()) {
-            Ok(res) => res,
-            Err(err) => {
-                handle_errors(err, None);
-                buf.clear();
+    let mut input = InputBuffer::new();
+
+    loop {
+        let prompt = if input.is_empty() {
+            println!();
+            format!("{}{}>>>{} ", BOLD, BLUE, RESET)
+        } else {
+            format!("{}{}...{} ", BOLD, BLUE, RESET)
+        };
+
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                input.take();
                 continue;
+            },
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                println!("Could not read line: {}", err);
+                break;
             }
         };
 
-        println!("{}", result.to_string_with_type());
-    }
-}
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+        }
 
-fn interpret(input: String) -> Result<(Tokens, Vec<Node>, Object), DynamicError> {
-    let mut lexer = Lexer::from(&input);
-    let tokens = lexer.tokenize()?;
+        if input.is_empty() && ReplCommand::is_command(&line) {
+            let output = match ReplCommand::parse(&line) {
+                Ok(ReplCommand::Quit) => break,
+                Ok(command) => command.run(&mut session),
+                Err(err) => Err(err),
+            };
+
+            match output {
+                Ok(Some(text)) => println!("{}", text),
+                Ok(None) => {},
+                Err(err) => report(&err, session.sources(), format),
+            };
+        } else {
+            input.push_line(&line);
+            if !input.is_complete() {
+                continue;
+            }
 
-    let mut parser = Parser::from(&tokens);
-    let ast = parser.parse()?;
+            match session.eval(&input.take()) {
+                Ok(result) if result.is(ObjectType::Void) => {},
+                Ok(result) => println!("{}", result.to_string_with_type()),
+                Err(err) => report(&err, session.sources(), format),
+            };
+        }
 
-    if let Node::Program(ast) = ast {
-        let mut evaluator = Evaluator::new(&ast);
-        let result = evaluator.eval()?;
+        if let Some(helper) = editor.helper_mut() {
+            helper.update_names(session.env());
+        }
+    }
 
-        return Ok((tokens.to_owned(), ast, result));
+    if let Some(path) = &history {
+        let _ = editor.save_history(path);
     }
+}
+
+fn parse(source: &str, file: FileId) -> Result<Program, DynamicError> {
+    let mut lexer = Lexer::with_file(source, file);
+    let tokens = lexer.tokenize()?;
 
-    error!("AST is not a program node.");
+    let mut parser = Parser::from(tokens);
+    parser.parse()
 }
 
-pub fn parse_file(path: &String) -> Result<(), DynamicError> {
-    let content = match fs::read_to_string(path) {
-        Ok(text) => text,
-        Err(err) => {
-            println!("Could not read file: {}", err);
-            exit(1)
-        } 
+fn run(program: Program) -> Result<(), DynamicError> {
+    let ast = match program {
+        Node::Program(ast) => ast,
+        _ => error!("AST is not a program node.")
     };
 
-    let (_, _, result) = interpret(content)?;
+    let mut evaluator = Evaluator::new(&ast);
+    let result = evaluator.eval()?;
 
     if !result.is(ObjectType::Void) {
         println!("{}", result);
@@ -105,21 +216,22 @@ pub fn parse_file(path: &String) -> Result<(), DynamicError> {
     Ok(())
 }
 
-fn handle_errors(err: DynamicError, path: Option<String>) {
-    let name = format!("{:?}", err);
-    let name = name.split_once(' ').unwrap_or(("UnknownError", "")).0;
-    let as_str = err.to_string()
-        .replace(r"{{path}}", &path.unwrap_or("unknown_path".to_string()))
-        .replace("&r", RED)
-        .replace("&g", GREEN)
-        .replace("&b", BLUE)
-        .replace("&c", CYAN)
-        .replace("&m", MAGENTA)
-        .replace("&y", YELLOW)
-        .replace("&-", RESET)
-        .replace("&_", UNDERLINE)
-        .replace("&*", BOLD);
-
-    println!("{}{}{}{} was thrown: {}{}", BLUE, name, RESET, RED, as_str, RESET);
+fn report(err: &DynamicError, sources: &SourceMap, format: ErrorFormat) {
+    let diagnostics = Diagnostic::all_from_error(err);
+
+    match format {
+        ErrorFormat::Human => {
+            let renderer = Renderer::stderr();
+            let rendered: Vec<String> = diagnostics.iter()
+                .map(|diagnostic| renderer.render(diagnostic, sources))
+                .collect();
+
+            eprintln!("{}", rendered.join("\n\n"));
+        },
+        ErrorFormat::Json => {
+            for diagnostic in diagnostics {
+                eprintln!("{}", diagnostic.to_json(sources));
+            }
+        },
+    }
 }
-
@@ -1,19 +1,31 @@
 use std::fmt::Display;
 
-use crate::lexer::token::{Token, TokenType};
+use crate::lexer::token::{Span, Token, TokenType};
 
+/// Creates a node struct with the given fields, followed by the `Span` it covers in the source
 macro_rules! create_struct {
     ($name:ident $(, $field_type:ty)*) => {
         #[derive(Debug, PartialEq, Clone)]
-        pub struct $name($(pub $field_type),*);
+        pub struct $name($(pub $field_type,)* pub Span);
+
+        impl $name {
+            pub fn span(&self) -> &Span {
+                let $name($(create_struct!(@ignore $field_type),)* span) = self;
+                span
+            }
+        }
     };
+
+    (@ignore $field_type:ty) => { _ };
 }
 
 create_struct!(StringLiteral, String);
-create_struct!(IntegerLiteral, i32);
-create_struct!(FloatLiteral, f32);
+create_struct!(IntegerLiteral, i64);
+create_struct!(FloatLiteral, f64);
 create_struct!(BooleanLiteral, bool);
 create_struct!(ListLiteral, Vec<Expression>);
+create_struct!(MapLiteral, Vec<(Expression, Expression)>);
+create_struct!(NullLiteral);
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Literal {
@@ -22,7 +34,22 @@ pub enum Literal {
     Float(FloatLiteral),
     Boolean(BooleanLiteral),
     List(ListLiteral),
-    Null
+    Map(MapLiteral),
+    Null(NullLiteral)
+}
+
+impl Literal {
+    pub fn span(&self) -> &Span {
+        match self {
+            Literal::String(literal) => literal.span(),
+            Literal::Integer(literal) => literal.span(),
+            Literal::Float(literal) => literal.span(),
+            Literal::Boolean(literal) => literal.span(),
+            Literal::List(literal) => literal.span(),
+            Literal::Map(literal) => literal.span(),
+            Literal::Null(literal) => literal.span(),
+        }
+    }
 }
 
 impl Display for Literal {
@@ -33,7 +60,8 @@ impl Display for Literal {
             Literal::Float(float) => write!(f, "{}", float.0),
             Literal::Boolean(boolean) => write!(f, "{}", boolean.0),
             Literal::List(list) => write!(f, "{:?}", list.0),
-            Literal::Null => write!(f, "null")
+            Literal::Map(map) => write!(f, "{:?}", map.0),
+            Literal::Null(_) => write!(f, "null")
         }
     }
 
@@ -80,6 +108,59 @@ pub enum Operator {
     Logical(LogicalOperator)
 }
 
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operator::Assignment(operator) => write!(f, "{}", operator),
+            Operator::Arithmetic(operator) => write!(f, "{}", operator),
+            Operator::Logical(operator) => write!(f, "{}", operator),
+        }
+    }
+}
+
+impl Display for ArithmeticOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ArithmeticOperator::Plus => "+",
+            ArithmeticOperator::Minus => "-",
+            ArithmeticOperator::Divide => "/",
+            ArithmeticOperator::Multiply => "*",
+            ArithmeticOperator::Modulo => "%",
+            ArithmeticOperator::Power => "^",
+        })
+    }
+}
+
+impl Display for AssignmentOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AssignmentOperator::PlusAssign => "+=",
+            AssignmentOperator::MinusAssign => "-=",
+            AssignmentOperator::DivideAssign => "/=",
+            AssignmentOperator::MultiplyAssign => "*=",
+            AssignmentOperator::ModuloAssign => "%=",
+            AssignmentOperator::PowerAssign => "^=",
+            AssignmentOperator::Assign => "=",
+        })
+    }
+}
+
+impl Display for LogicalOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LogicalOperator::Or => "||",
+            LogicalOperator::And => "&&",
+            LogicalOperator::Not => "!",
+            LogicalOperator::Equal => "==",
+            LogicalOperator::NotEqual => "!=",
+            LogicalOperator::LesserThan => "<",
+            LogicalOperator::GreaterThan => ">",
+            LogicalOperator::LesserThanEqual => "<=",
+            LogicalOperator::GreaterThanEqual => ">=",
+        })
+    }
+}
+
 pub fn op_token_to_arithmetic(op: &Token) -> Option<ArithmeticOperator> {
     match op.token_type {
         TokenType::Plus => Some(ArithmeticOperator::Plus),
@@ -136,8 +217,37 @@ create_struct!(Assignment, Identifier, Box<Node>);
 create_struct!(Identifier, String);
 create_struct!(BinaryExpression, Box<Expression>, Operator, Box<Expression>);
 create_struct!(UnaryExpression, Operator, Box<Expression>);
-create_struct!(FunctionCallExpression, Identifier, Vec<Expression>);
-create_struct!(FunctionDeclareExpression, Identifier, Vec<Identifier>, Box<BlockStatement>);
+create_struct!(FunctionCallExpression, Box<Expression>, Vec<Expression>);
+// A function literal, named after the variable it is assigned to if it is declared with `name = (params) { ... }`,
+// in which case it also has the `##` doc comments before the declaration
+create_struct!(FunctionDeclareExpression, Option<Identifier>, Vec<Identifier>, Box<BlockStatement>, Option<String>);
+
+impl FunctionDeclareExpression {
+    /// Name and parameters of the function, like `greet(name, greeting)`
+    pub fn signature(&self) -> String {
+        let parameters: Vec<&str> = self.1.iter().map(|parameter| parameter.0.as_str()).collect();
+        format!("{}({})", self.0.as_ref().map_or("<anonymous>", |name| name.0.as_str()), parameters.join(", "))
+    }
+
+    pub fn doc(&self) -> Option<&str> {
+        self.3.as_deref()
+    }
+}
+create_struct!(GroupExpression, Box<Expression>);
+create_struct!(IndexExpression, Box<Expression>, Box<Expression>);
+// `target[start:end]`, a missing bound stands for the start or end of the list
+create_struct!(SliceExpression, Box<Expression>, Option<Box<Expression>>, Option<Box<Expression>>);
+create_struct!(IndexAssignment, IndexExpression, Box<Expression>);
+// `start..end`, or `start..=end` if it is inclusive, with an optional `step`
+create_struct!(RangeExpression, Box<Expression>, Box<Expression>, bool, Option<Box<Expression>>);
+// A string with interpolations, like `"Hello ${name}"`
+create_struct!(TemplateExpression, Vec<TemplatePart>);
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TemplatePart {
+    Text(String),
+    Expression(Expression),
+}
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expression {
@@ -146,10 +256,36 @@ pub enum Expression {
     IdentifierExpr(Identifier),
     BinaryExpr(BinaryExpression),
     UnaryExpr(UnaryExpression),
-    GroupExpr(Box<Expression>),
+    GroupExpr(GroupExpression),
     BlockExpr(BlockStatement),
     FunctionCallExpr(FunctionCallExpression),
     FunctionDeclareExpr(FunctionDeclareExpression),
+    IndexExpr(IndexExpression),
+    SliceExpr(SliceExpression),
+    IndexAssignmentExpr(IndexAssignment),
+    RangeExpr(RangeExpression),
+    TemplateExpr(TemplateExpression),
+}
+
+impl Expression {
+    pub fn span(&self) -> &Span {
+        match self {
+            Expression::AssignmentExpr(expression) => expression.span(),
+            Expression::LiteralExpr(expression) => expression.span(),
+            Expression::IdentifierExpr(expression) => expression.span(),
+            Expression::BinaryExpr(expression) => expression.span(),
+            Expression::UnaryExpr(expression) => expression.span(),
+            Expression::GroupExpr(expression) => expression.span(),
+            Expression::BlockExpr(expression) => expression.span(),
+            Expression::FunctionCallExpr(expression) => expression.span(),
+            Expression::FunctionDeclareExpr(expression) => expression.span(),
+            Expression::IndexExpr(expression) => expression.span(),
+            Expression::SliceExpr(expression) => expression.span(),
+            Expression::IndexAssignmentExpr(expression) => expression.span(),
+            Expression::RangeExpr(expression) => expression.span(),
+            Expression::TemplateExpr(expression) => expression.span(),
+        }
+    }
 }
 
 impl Display for Expression {
@@ -174,6 +310,8 @@ create_struct!(IfStatement, Expression, Box<BlockStatement>, Option<Box<Node>>);
 create_struct!(ElseStatement, Box<BlockStatement>);
 create_struct!(WhileStatement, Expression, Box<BlockStatement>);
 create_struct!(ForStatement, Expression, Expression, Expression, Box<BlockStatement>);
+// `for item in iterable` or `for key, item in iterable`
+create_struct!(ForInStatement, Vec<Identifier>, Expression, Box<BlockStatement>);
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Node {
@@ -187,5 +325,41 @@ pub enum Node {
     IfStatement(IfStatement),
     ElseStatement(ElseStatement),
     WhileStatement(WhileStatement),
-    ForStatement(ForStatement),
+    ForStatement(Box<ForStatement>),
+    ForInStatement(Box<ForInStatement>),
+}
+
+impl Node {
+    /// Function declared by the statement, if it is a `name = (params) { ... }` declaration
+    pub fn declared_function(&self) -> Option<&FunctionDeclareExpression> {
+        let Node::ExpressionStatement(ExpressionStatement(Expression::AssignmentExpr(assignment), _)) = self else {
+            return None;
+        };
+
+        match assignment.1.as_ref() {
+            Node::ExpressionStatement(ExpressionStatement(Expression::FunctionDeclareExpr(function), _)) => Some(function),
+            _ => None,
+        }
+    }
+
+    /// Span of the node, a program covers everything from its first to its last statement
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Program(nodes) => match (nodes.first(), nodes.last()) {
+                (Some(first), Some(last)) => first.span().to(&last.span()),
+                _ => Span::default(),
+            },
+            Node::BlockStatement(node) => node.span().to_owned(),
+            Node::ExpressionStatement(node) => node.span().to_owned(),
+            Node::EmptyStatement(node) => node.span().to_owned(),
+            Node::ReturnStatement(node) => node.span().to_owned(),
+            Node::ContinueStatement(node) => node.span().to_owned(),
+            Node::BreakStatement(node) => node.span().to_owned(),
+            Node::IfStatement(node) => node.span().to_owned(),
+            Node::ElseStatement(node) => node.span().to_owned(),
+            Node::WhileStatement(node) => node.span().to_owned(),
+            Node::ForStatement(node) => node.span().to_owned(),
+            Node::ForInStatement(node) => node.span().to_owned(),
+        }
+    }
 }
@@ -0,0 +1,25 @@
+use super::ast::Node;
+
+/// Generates Markdown documentation for the functions declared at the top level of a program,
+/// using the `##` doc comments before their declarations
+pub fn generate(program: &Node) -> String {
+    let nodes = match program {
+        Node::Program(nodes) => nodes.as_slice(),
+        node => std::slice::from_ref(node),
+    };
+
+    let mut sections = Vec::new();
+    for function in nodes.iter().filter_map(Node::declared_function) {
+        let mut section = format!("## `{}`\n", function.signature());
+
+        if let Some(doc) = function.doc() {
+            section.push('\n');
+            section.push_str(doc);
+            section.push('\n');
+        }
+
+        sections.push(section);
+    }
+
+    sections.join("\n")
+}
@@ -1,17 +1,43 @@
-use std::{error::Error, vec};
+use std::vec;
 
-use crate::{error, errors::{DynamicError, ParserError}, evaluator::object::FUNCTION_PREFIX, lexer::token::{Token, TokenLiteral, TokenType, Tokens}, parser::ast::Literal, utils::unwrap_result};
+use crate::{error, errors::{DynamicError, ParserError, ParserErrors, ParserWarning}, lexer::token::{Span, TemplatePart, Token, TokenLiteral, TokenType, Tokens}, parser::ast::Literal, utils::unwrap_result};
 
-use self::ast::{assignment_to_arithmetic, op_token_to_arithmetic, op_token_to_assignment, op_token_to_logical, BlockStatement, EmptyStatement, Expression, ExpressionStatement, Identifier, Node, Program};
+use self::ast::{assignment_to_arithmetic, op_token_to_arithmetic, op_token_to_assignment, op_token_to_logical, BlockStatement, EmptyStatement, Expression, ExpressionStatement, FunctionDeclareExpression, Identifier, Node, Program};
 
 pub mod ast;
+pub mod docs;
+pub mod printer;
 
 type ParserResult<T> = Result<T, DynamicError>;
 
+/// Result of a parse that recovers from errors, with every statement that could be parsed
+pub struct ParseOutput {
+    pub program: Program,
+    pub errors: Vec<DynamicError>,
+    /// Problems which do not keep the program from running, like unreachable code
+    pub warnings: Vec<DynamicError>,
+}
+
+impl ParseOutput {
+    /// The program, or every syntax error found if there are any
+    pub fn into_program(mut self) -> Result<Program, DynamicError> {
+        match self.errors.len() {
+            0 => Ok(self.program),
+            1 => Err(self.errors.remove(0)),
+            _ => error!(ParserErrors(self.errors)),
+        }
+    }
+}
+
 pub struct Parser<'a> {
     pub tokens: &'a Tokens,
-    warnings: Vec<Box<dyn Error>>,
+    errors: Vec<DynamicError>,
+    warnings: Vec<DynamicError>,
     current: usize,
+    /// Number of loops around the statement being parsed, for `break` and `continue`
+    loop_depth: usize,
+    /// Whether an `if` or `while` condition is being parsed, where `(x) {` starts the body rather than a function
+    in_condition: bool,
 }
 
 impl<'a> Parser<'a> {
@@ -19,69 +45,116 @@ impl<'a> Parser<'a> {
         Self {
             tokens,
             current: 0,
+            errors: Vec::new(),
             warnings: Vec::new(),
+            loop_depth: 0,
+            in_condition: false,
         }
     }
 
+    /// Parses the program, failing with every syntax error found if there are any
     pub fn parse(&mut self) -> Result<Program, DynamicError> {
-        Ok(Node::Program(self.parse_statements()?))
+        self.parse_recovering().into_program()
     }
 
-    fn parse_statements(&mut self) -> ParserResult<Vec<Node>> {
+    /// Parses the program, skipping to the next statement after an error instead of stopping
+    pub fn parse_recovering(&mut self) -> ParseOutput {
         let mut statements: Vec<Node> = Vec::new();
 
         while !self.is_at_end() {
-            let statement = self.declaration()?;
-            statements.push(statement);
+            if let Some(statement) = self.recovering_declaration() {
+                statements.push(statement);
+            }
         }
 
-        Ok(statements)
+        self.check_reachable(&statements);
+        ParseOutput {
+            program: Node::Program(statements),
+            errors: std::mem::take(&mut self.errors),
+            warnings: std::mem::take(&mut self.warnings),
+        }
     }
 
-    fn declaration(&mut self) -> ParserResult<Node> {
-        if self.check(TokenType::Symbol) {
-            if unwrap_result(self.lookahead())?.token_type == TokenType::Assign {
-                return Ok(self.var_declaration()?)
+    /// Parses a declaration, recording its error and synchronizing if it fails
+    fn recovering_declaration(&mut self) -> Option<Node> {
+        let start = self.current;
+
+        match self.declaration() {
+            Ok(statement) => Some(statement),
+            Err(err) => {
+                self.errors.push(err);
+                self.synchronize();
+
+                // Always make progress, e.g. on a stray closing brace
+                if self.current == start {
+                    self.advance();
+                }
+
+                None
+            }
+        }
+    }
+
+    /// Skips tokens until the end of the statement, a closing brace or the start of another statement
+    fn synchronize(&mut self) {
+        while let Some(token) = self.peek() {
+            match token.token_type {
+                TokenType::EndOfLine => {
+                    self.advance();
+                    return;
+                },
+                TokenType::EndOfFile | TokenType::RightBrace
+                | TokenType::If | TokenType::While | TokenType::For
+                | TokenType::Return | TokenType::Break | TokenType::Continue => return,
+                _ => {
+                    self.advance();
+                }
             }
         }
+    }
 
-        Ok(self.statement()?)
+    fn declaration(&mut self) -> ParserResult<Node> {
+        if self.check(TokenType::Symbol) && unwrap_result(self.lookahead())?.token_type == TokenType::Assign {
+            return self.var_declaration();
+        }
+
+        self.statement()
     }
 
     fn var_declaration(&mut self) -> ParserResult<Node> {
+        let start = self.current;
         let symbol = self.consume(TokenType::Symbol)?;
-        let name = match unwrap_result(symbol.value)? {
+        let identifier_span = symbol.span();
+        let doc = symbol.doc.to_owned();
+        let name = match unwrap_result(symbol.value.to_owned())? {
             TokenLiteral::String(name) => name,
             _ => error!(ParserError::InvalidToken { 
                 expected: vec![TokenType::Symbol],
-                found: symbol.token_type,
-                pos: symbol.start,
+                found: symbol.token_type.to_owned(),
+                span: symbol.span(),
             }),
         };
         
         self.consume(TokenType::Assign)?;
-        let old_current = self.current;
-        
-        // Attempt to collect parameters for function declaration
-        if self.matches(TokenType::LeftParen) {
-            match self.collect_parameters() {
-                Ok(parameters) => {
-                    if self.check(TokenType::LeftBrace) {
-                        return self.func_declaration(Identifier(name), parameters);
-                    }
-                },
-                Err(_) => {} // Collecting parameters failed
-            };
+        let mut initializer = self.statement()?;
+
+        // Functions declared as `name = (params) { ... }` take the name of their variable
+        if let Node::ExpressionStatement(ExpressionStatement(Expression::FunctionDeclareExpr(function), _)) = &mut initializer {
+            if function.0.is_none() {
+                function.0 = Some(Identifier(name.to_owned(), identifier_span.to_owned()));
+                function.3 = doc;
+            }
         }
-        self.current = old_current; // Reset current to before the failed attempt
 
-        let initializer = self.statement()?;
+        let span = self.span_from(start);
 
         Ok(Node::ExpressionStatement(ExpressionStatement(
             Expression::AssignmentExpr(ast::Assignment(
-                ast::Identifier(name),
+                ast::Identifier(name, identifier_span),
                 Box::from(initializer),
-            ))
+                span.to_owned(),
+            )),
+            span,
         )))
     }
     
@@ -93,108 +166,162 @@ impl<'a> Parser<'a> {
                 break;
             }
             
-            let symbol = self.consume(TokenType::Symbol)?;
-            let name = match unwrap_result(symbol.value)? {
-                TokenLiteral::String(name) => name,
-                _ => error!(ParserError::InvalidToken {
-                    expected: vec![TokenType::Symbol],
-                    found: symbol.token_type,
-                    pos: symbol.start,
-                }),
-            };
-            
-            arguments.push(Identifier(name));
+            arguments.push(self.identifier()?);
             
-            if !self.matches(TokenType::Comma) {
-                if self.matches(TokenType::RightParen) {
-                    break;
-                }
+            if !self.matches(TokenType::Comma) && self.matches(TokenType::RightParen) {
+                break;
             }
         }
 
         Ok(arguments)
     }
 
-    fn func_declaration(&mut self, mut identifier: Identifier, parameters: Vec<Identifier>) -> ParserResult<Node> {
-        let body = self.block()?;
+    fn identifier(&mut self) -> ParserResult<Identifier> {
+        let symbol = self.consume(TokenType::Symbol)?;
 
-        identifier.0 = format!("{}{}", FUNCTION_PREFIX, identifier.0);
+        match unwrap_result(symbol.value.to_owned())? {
+            TokenLiteral::String(name) => Ok(Identifier(name, symbol.span())),
+            _ => error!(ParserError::InvalidToken {
+                expected: vec![TokenType::Symbol],
+                found: symbol.token_type.to_owned(),
+                span: symbol.span(),
+            }),
+        }
+    }
 
-        Ok(Node::ExpressionStatement(ExpressionStatement(
-            Expression::FunctionDeclareExpr(
-                ast::FunctionDeclareExpression(
-                    identifier,
-                    parameters,
-                    Box::from(body)
-                )
-            )
-        )))
+    /// Parses a block where `break` and `continue` refer to a loop
+    fn loop_body(&mut self) -> ParserResult<BlockStatement> {
+        self.loop_depth += 1;
+        let body = self.block();
+        self.loop_depth -= 1;
+
+        body
+    }
+
+    /// Parses the body of a function, which is outside of every loop around it
+    ///
+    /// Unlike other blocks it is part of an expression, so a `;` after it is left to end the statement.
+    fn function_body(&mut self) -> ParserResult<BlockStatement> {
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let in_condition = std::mem::take(&mut self.in_condition);
+        let body = self.braced_block();
+        self.loop_depth = loop_depth;
+        self.in_condition = in_condition;
+
+        body
     }
 
     fn block(&mut self) -> ParserResult<BlockStatement> {
+        let block = self.braced_block()?;
+        let _ = self.consume(TokenType::EndOfLine);
+
+        Ok(block)
+    }
+
+    fn braced_block(&mut self) -> ParserResult<BlockStatement> {
+        let start = self.current;
         self.consume(TokenType::LeftBrace)?;
         let mut statements: Vec<Node> = Vec::new();
 
         while !self.is_at_end() && !self.check(TokenType::RightBrace) {
-            statements.push(self.declaration()?);
+            if let Some(statement) = self.recovering_declaration() {
+                statements.push(statement);
+            }
         }
 
         self.consume(TokenType::RightBrace)?;
-        let _ = self.consume(TokenType::EndOfLine);
+        self.check_reachable(&statements);
+
+        Ok(ast::BlockStatement(statements, self.span_from(start)))
+    }
+
+    /// Records a warning for the statements after a `return`, `break` or `continue`, which can never run
+    fn check_reachable(&mut self, statements: &[Node]) {
+        let Some(exit) = statements.iter().position(|statement| matches!(statement,
+            Node::ReturnStatement(_) | Node::BreakStatement(_) | Node::ContinueStatement(_)
+        )) else {
+            return;
+        };
+
+        let keyword = match statements[exit] {
+            Node::ReturnStatement(_) => "return",
+            Node::BreakStatement(_) => "break",
+            _ => "continue",
+        };
+
+        let mut unreachable = statements[exit + 1..].iter()
+            .filter(|statement| !matches!(statement, Node::EmptyStatement(_)))
+            .map(Node::span);
 
-        Ok(ast::BlockStatement(statements))
+        if let Some(first) = unreachable.next() {
+            let span = unreachable.next_back().map_or(first.to_owned(), |last| first.to(&last));
+            self.warnings.push(ParserWarning::UnreachableCode { keyword: keyword.to_owned(), span }.into());
+        }
     }
 
     fn statement(&mut self) -> ParserResult<Node> {
+        let start = self.current;
+
         if self.matches(TokenType::EndOfLine) {
-            return Ok(Node::EmptyStatement(EmptyStatement()));
+            return Ok(Node::EmptyStatement(EmptyStatement(self.span_from(start))));
         }
 
         if self.matches(TokenType::If) {
-            return self.if_statement();
+            return self.if_statement(start);
         }
 
         if self.matches(TokenType::While) {
-            return self.while_statement();
+            return self.while_statement(start);
         }
 
         if self.matches(TokenType::For) {
-            return self.for_statement();
+            return self.for_statement(start);
         }
 
         if self.matches(TokenType::Break) {
-            return Ok(Node::BreakStatement(ast::BreakStatement()));
+            self.check_in_loop("break", start);
+            return Ok(Node::BreakStatement(ast::BreakStatement(self.span_from(start))));
         }
 
         if self.matches(TokenType::Continue) {
-            return Ok(Node::ContinueStatement(ast::ContinueStatement()));
+            self.check_in_loop("continue", start);
+            return Ok(Node::ContinueStatement(ast::ContinueStatement(self.span_from(start))));
         }
 
         if self.matches(TokenType::Return) {
-            return self.return_statement();
+            return self.return_statement(start);
         }
 
         Ok(Node::ExpressionStatement(self.expression_statement()?))
     }
 
-    fn else_statement(&mut self) -> ParserResult<Node> {
+    /// Records an error for a `break` or `continue` outside of a loop, the statement itself is kept
+    fn check_in_loop(&mut self, keyword: &str, start: usize) {
+        if self.loop_depth == 0 {
+            let span = self.span_from(start);
+            self.errors.push(ParserError::OutsideLoop { keyword: keyword.to_owned(), span }.into());
+        }
+    }
+
+    fn else_statement(&mut self, start: usize) -> ParserResult<Node> {
         let body = self.block()?;
-        Ok(Node::ElseStatement(ast::ElseStatement(Box::from(body))))
+        Ok(Node::ElseStatement(ast::ElseStatement(Box::from(body), self.span_from(start))))
     }
 
-    fn if_statement(&mut self) -> ParserResult<Node> {
-        let condition = self.expression()?;
+    fn if_statement(&mut self, start: usize) -> ParserResult<Node> {
+        let condition = self.condition()?;
         let body = self.block()?;
 
+        let else_start = self.current;
         let maybe_else = if let Some(token) = self.peek() {
             match token.token_type {
                 TokenType::ElIf => {
                     self.consume(TokenType::ElIf)?;
-                    Some(Box::from(self.if_statement()?))
+                    Some(Box::from(self.if_statement(else_start)?))
                 },
                 TokenType::Else => {
                     self.consume(TokenType::Else)?;
-                    Some(Box::from(self.else_statement()?))
+                    Some(Box::from(self.else_statement(else_start)?))
                 },
                 _ => None
             }
@@ -207,23 +334,29 @@ impl<'a> Parser<'a> {
                 condition,
                 Box::from(body),
                 maybe_else,
+                self.span_from(start),
             )
         ))
     }
 
-    fn while_statement(&mut self) -> ParserResult<Node> {
-        let condition = self.expression()?;
-        let body = self.block()?;
+    fn while_statement(&mut self, start: usize) -> ParserResult<Node> {
+        let condition = self.condition()?;
+        let body = self.loop_body()?;
 
         Ok(Node::WhileStatement(
             ast::WhileStatement(
                 condition,
                 Box::from(body),
+                self.span_from(start),
             )
         ))
     }
 
-    fn for_statement(&mut self) -> ParserResult<Node> {
+    fn for_statement(&mut self, start: usize) -> ParserResult<Node> {
+        if self.is_for_in() {
+            return self.for_in_statement(start);
+        }
+
         let _ = self.consume(TokenType::LeftParen);
         let variable = self.var_declaration()?;
 
@@ -233,24 +366,52 @@ impl<'a> Parser<'a> {
         let assignment = self.assignment()?;
         let _ = self.consume(TokenType::RightParen);
 
-        let body = self.block()?;
+        let body = self.loop_body()?;
 
         let variable = match variable {
-            Node::ExpressionStatement(ExpressionStatement(assignment)) => assignment,
-            _ => error!(ParserError::InvalidStatement)
+            Node::ExpressionStatement(ExpressionStatement(assignment, _)) => assignment,
+            variable => error!(ParserError::InvalidStatement { span: variable.span() })
         };
 
-        Ok(Node::ForStatement(
+        Ok(Node::ForStatement(Box::new(
             ast::ForStatement(
                 variable,
                 condition,
                 assignment,
                 Box::from(body),
+                self.span_from(start),
             )
-        ))
+        )))
     }
 
-    fn return_statement(&mut self) -> ParserResult<Node> {
+    /// Whether the loop after `for` is `for item in ...` or `for key, item in ...`
+    fn is_for_in(&self) -> bool {
+        let token_type = |offset: usize| self.tokens.get(self.current + offset).map(|token| &token.token_type);
+
+        token_type(0) == Some(&TokenType::Symbol) && matches!(token_type(1), Some(TokenType::In | TokenType::Comma))
+    }
+
+    fn for_in_statement(&mut self, start: usize) -> ParserResult<Node> {
+        let mut variables = vec![self.identifier()?];
+        if self.matches(TokenType::Comma) {
+            variables.push(self.identifier()?);
+        }
+
+        self.consume(TokenType::In)?;
+        let iterable = self.condition()?;
+        let body = self.loop_body()?;
+
+        Ok(Node::ForInStatement(Box::new(
+            ast::ForInStatement(
+                variables,
+                iterable,
+                Box::from(body),
+                self.span_from(start),
+            )
+        )))
+    }
+
+    fn return_statement(&mut self, start: usize) -> ParserResult<Node> {
         let return_value = if !self.matches(TokenType::EndOfLine) {
             Some(self.expression()?)
         } else {
@@ -262,27 +423,66 @@ impl<'a> Parser<'a> {
         }
 
         Ok(Node::ReturnStatement(ast::ReturnStatement(
-            return_value
+            return_value,
+            self.span_from(start),
         )))
     }
 
     fn expression_statement(&mut self) -> ParserResult<ExpressionStatement> {
         let expression = self.expression()?;
-        if let Some(token) = self.previous() {
-            if token.token_type != TokenType::RightBrace {
+        // The `;` is optional after an expression ending in a block, e.g. a function
+        match self.previous() {
+            Some(token) if token.token_type == TokenType::RightBrace => {
+                let _ = self.consume(TokenType::EndOfLine);
+            },
+            Some(_) => {
                 self.consume(TokenType::EndOfLine)?;
-            }
+            },
+            None => {},
         }
-        Ok(ExpressionStatement(expression))
+
+        let span = expression.span().to_owned();
+        Ok(ExpressionStatement(expression, span))
     }
 
     fn expression(&mut self) -> ParserResult<Expression> {
         self.assignment()
     }
 
+    /// Parses the condition of an `if` or `while`, or what a `for` loop goes through
+    fn condition(&mut self) -> ParserResult<Expression> {
+        let in_condition = std::mem::replace(&mut self.in_condition, true);
+        let condition = self.expression();
+        self.in_condition = in_condition;
+
+        condition
+    }
+
+    /// Parses an expression between delimiters, e.g. an argument, where function literals are allowed again
+    fn nested_expression(&mut self) -> ParserResult<Expression> {
+        let in_condition = std::mem::take(&mut self.in_condition);
+        let expression = self.expression();
+        self.in_condition = in_condition;
+
+        expression
+    }
+
     fn assignment(&mut self) -> ParserResult<Expression> {
         let expression = self.or()?;
 
+        if let Expression::IndexExpr(target) = &expression {
+            if self.matches(TokenType::Assign) {
+                let value = self.assignment()?;
+                let span = expression.span().to(value.span());
+
+                return Ok(Expression::IndexAssignmentExpr(ast::IndexAssignment(
+                    target.to_owned(),
+                    Box::from(value),
+                    span,
+                )));
+            }
+        }
+
         if self.match_one_of(vec![
             TokenType::PlusAssign,
             TokenType::MinusAssign,
@@ -293,22 +493,29 @@ impl<'a> Parser<'a> {
             let operator = unwrap_result(self.previous())?.to_owned();
             let value = self.assignment()?;
 
-            if let Expression::IdentifierExpr(identifier) = &expression {
-                let ast_op = unwrap_result(op_token_to_assignment(&operator))?;
-                let arithmetic_op = unwrap_result(assignment_to_arithmetic(&ast_op))?;
-
-                return Ok(Expression::AssignmentExpr(ast::Assignment(
-                    identifier.to_owned(),
-                    Box::from(Node::ExpressionStatement(
-                        ExpressionStatement(
-                            Expression::BinaryExpr(ast::BinaryExpression(
-                                Box::new(expression),
-                                ast::Operator::Arithmetic(arithmetic_op),
-                                Box::new(value),
-                            ))
-                        )
-                    )),
-                )))
+            let ast_op = unwrap_result(op_token_to_assignment(&operator))?;
+            let arithmetic_op = unwrap_result(assignment_to_arithmetic(&ast_op))?;
+            let span = expression.span().to(value.span());
+            let target = expression.to_owned();
+            let operation = Expression::BinaryExpr(ast::BinaryExpression(
+                Box::new(expression),
+                ast::Operator::Arithmetic(arithmetic_op),
+                Box::new(value),
+                span.to_owned(),
+            ));
+
+            match target {
+                Expression::IdentifierExpr(identifier) => return Ok(Expression::AssignmentExpr(ast::Assignment(
+                    identifier,
+                    Box::from(Node::ExpressionStatement(ExpressionStatement(operation, span.to_owned()))),
+                    span,
+                ))),
+                Expression::IndexExpr(target) => return Ok(Expression::IndexAssignmentExpr(ast::IndexAssignment(
+                    target,
+                    Box::from(operation),
+                    span,
+                ))),
+                target => return Ok(target),
             }
         }
 
@@ -320,10 +527,12 @@ impl<'a> Parser<'a> {
 
         while self.matches(TokenType::Or) {
             let right = self.and()?;
+            let span = expression.span().to(right.span());
             expression = Expression::BinaryExpr(ast::BinaryExpression(
                 Box::new(expression), 
                 ast::Operator::Logical(ast::LogicalOperator::Or), 
-                Box::new(right)
+                Box::new(right),
+                span,
             ));
         }
 
@@ -335,10 +544,12 @@ impl<'a> Parser<'a> {
 
         while self.matches(TokenType::And) {
             let right = self.equality()?;
+            let span = expression.span().to(right.span());
             expression = Expression::BinaryExpr(ast::BinaryExpression(
                 Box::new(expression), 
                 ast::Operator::Logical(ast::LogicalOperator::And), 
-                Box::new(right)
+                Box::new(right),
+                span,
             ));
         }
 
@@ -355,14 +566,16 @@ impl<'a> Parser<'a> {
             match op_token_to_logical(&operator) {
                 None => error!(ParserError::InvalidToken {
                     expected: vec![TokenType::Equal, TokenType::NotEqual],
-                    found: operator.token_type,
-                    pos: operator.start,
+                    found: operator.token_type.to_owned(),
+                    span: operator.span(),
                 }),
                 Some(op) => {
+                    let span = expression.span().to(right.span());
                     expression = Expression::BinaryExpr(ast::BinaryExpression(
                         Box::new(expression),
                         ast::Operator::Logical(op),
                         Box::new(right),
+                        span,
                     ))
                 }
             }
@@ -372,7 +585,7 @@ impl<'a> Parser<'a> {
     }
 
     fn comparison(&mut self) -> ParserResult<Expression> {
-        let mut expression = self.addition()?;
+        let mut expression = self.range()?;
 
         while self.match_one_of(vec![
             TokenType::LesserThan,
@@ -381,20 +594,52 @@ impl<'a> Parser<'a> {
             TokenType::GreaterThanEqual,
         ]) {
             let operator = unwrap_result(self.previous())?.to_owned();
-            let right = self.addition()?;
+            let right = self.range()?;
 
             let comparison_operator = unwrap_result(op_token_to_logical(&operator))?;
 
+            let span = expression.span().to(right.span());
             expression = Expression::BinaryExpr(ast::BinaryExpression(
                 Box::new(expression), 
                 ast::Operator::Logical(comparison_operator), 
-                Box::new(right)
+                Box::new(right),
+                span,
             ));
         }
 
         Ok(expression)
     }
 
+    /// Parses `start..end` or `start..=end`, optionally followed by `step` and the step
+    fn range(&mut self) -> ParserResult<Expression> {
+        let expression = self.addition()?;
+
+        if !self.match_one_of(vec![TokenType::Range, TokenType::RangeInclusive]) {
+            return Ok(expression);
+        }
+
+        let inclusive = unwrap_result(self.previous())?.token_type == TokenType::RangeInclusive;
+        let end = self.addition()?;
+        let mut span = expression.span().to(end.span());
+
+        // `step` is only a keyword right after a range, so it can still be used as a name
+        let mut step = None;
+        if self.peek().is_some_and(|token| token.token_type == TokenType::Symbol && token.value == Some(TokenLiteral::String(String::from("step")))) {
+            self.advance();
+            let value = self.addition()?;
+            span = span.to(value.span());
+            step = Some(Box::from(value));
+        }
+
+        Ok(Expression::RangeExpr(ast::RangeExpression(
+            Box::from(expression),
+            Box::from(end),
+            inclusive,
+            step,
+            span,
+        )))
+    }
+
     fn addition(&mut self) -> ParserResult<Expression> {
         let mut expression = self.multiplication()?;
 
@@ -404,10 +649,12 @@ impl<'a> Parser<'a> {
 
             let arithmetic_operator = unwrap_result(op_token_to_arithmetic(&operator))?;
 
+            let span = expression.span().to(right.span());
             expression = Expression::BinaryExpr(ast::BinaryExpression(
                 Box::new(expression),
                 ast::Operator::Arithmetic(arithmetic_operator),
                 Box::new(right),
+                span,
             ));
         }
 
@@ -423,10 +670,12 @@ impl<'a> Parser<'a> {
 
             let arithmetic_operator = unwrap_result(op_token_to_arithmetic(&operator))?;
 
+            let span = expression.span().to(right.span());
             expression = Expression::BinaryExpr(ast::BinaryExpression(
                 Box::new(expression),
                 ast::Operator::Arithmetic(arithmetic_operator),
                 Box::new(right),
+                span,
             ));
         }
 
@@ -439,10 +688,12 @@ impl<'a> Parser<'a> {
         if self.matches(TokenType::Power) {
             let right = self.unary()?;
 
+            let span = expression.span().to(right.span());
             expression = Expression::BinaryExpr(ast::BinaryExpression(
                 Box::new(expression),
                 ast::Operator::Arithmetic(ast::ArithmeticOperator::Power),
                 Box::new(right),
+                span,
             ));
         }
 
@@ -450,6 +701,7 @@ impl<'a> Parser<'a> {
     }
 
     fn unary(&mut self) -> ParserResult<Expression> {
+        let start = self.current;
         if self.match_one_of(vec![TokenType::Minus, TokenType::Not]) {
             let operator = unwrap_result(self.previous())?.to_owned();
             let right = self.unary()?;
@@ -460,7 +712,7 @@ impl<'a> Parser<'a> {
                 _ => error!(ParserError::InvalidToken {
                     expected: vec![TokenType::Minus, TokenType::Not],
                     found: operator.token_type.to_owned(),
-                    pos: operator.start,
+                    span: operator.span(),
                 })
             };
 
@@ -469,18 +721,20 @@ impl<'a> Parser<'a> {
                     ast::UnaryExpression(
                         unary_operator,
                         Box::new(right),
+                        self.span_from(start),
                     )
                 )),
                 ast::Operator::Logical(_) => Ok(Expression::UnaryExpr(
                     ast::UnaryExpression(
                         unary_operator,
                         Box::new(right),
+                        self.span_from(start),
                     )
                 )),
                 _ => error!(ParserError::InvalidToken {
                     expected: vec![TokenType::Minus, TokenType::Not],
                     found: operator.token_type.to_owned(),
-                    pos: operator.start,
+                    span: operator.span(),
                 })
             };
         }
@@ -489,24 +743,51 @@ impl<'a> Parser<'a> {
     }
 
     fn call(&mut self) -> ParserResult<Expression> {
-        let identifier = unwrap_result(self.peek())?.to_owned();
-        if self.matches_all_in_order(vec![TokenType::Symbol, TokenType::LeftParen]) {
-            return Ok(self.finish_call(identifier.to_owned())?);
+        let start = self.current;
+        let mut expression = self.primary()?;
+
+        // Anything evaluating to a function can be called, including the result of another call
+        loop {
+            if self.matches(TokenType::LeftParen) {
+                expression = self.finish_call(start, expression)?;
+            } else if self.matches(TokenType::LeftBracket) {
+                expression = self.finish_index(start, expression)?;
+            } else {
+                break;
+            }
         }
-        
-        Ok(self.primary()?)
+
+        Ok(expression)
     }
 
-    fn finish_call(&mut self, identifier: Token) -> ParserResult<Expression> {
-        let name = match unwrap_result(identifier.value)? {
-            TokenLiteral::String(name) => format!("{}{}", FUNCTION_PREFIX, name),
-            _ => error!(ParserError::InvalidToken {
-                expected: vec![TokenType::String],
-                found: identifier.token_type.to_owned(),
-                pos: identifier.start,
-            })
+    /// Parses `[index]` or `[start:end]` after `target`, either bound of a slice can be left out
+    fn finish_index(&mut self, start: usize, target: Expression) -> ParserResult<Expression> {
+        let from = if self.check(TokenType::Colon) {
+            None
+        } else {
+            Some(Box::from(self.nested_expression()?))
         };
 
+        let is_slice = from.is_none() || self.check(TokenType::Colon);
+        let mut to = None;
+        if is_slice {
+            self.consume(TokenType::Colon)?;
+
+            if !self.check(TokenType::RightBracket) {
+                to = Some(Box::from(self.nested_expression()?));
+            }
+        }
+
+        self.consume(TokenType::RightBracket)?;
+        let span = self.span_from(start);
+
+        Ok(match from {
+            Some(index) if !is_slice => Expression::IndexExpr(ast::IndexExpression(Box::from(target), index, span)),
+            from => Expression::SliceExpr(ast::SliceExpression(Box::from(target), from, to, span)),
+        })
+    }
+
+    fn finish_call(&mut self, start: usize, callee: Expression) -> ParserResult<Expression> {
         let mut arguments: Vec<Expression> = Vec::new();
 
         loop {
@@ -514,7 +795,7 @@ impl<'a> Parser<'a> {
                 break;
             }
 
-            arguments.push(self.expression()?);
+            arguments.push(self.nested_expression()?);
 
             if !self.matches(TokenType::Comma) {
                 self.consume(TokenType::RightParen)?;
@@ -523,55 +804,104 @@ impl<'a> Parser<'a> {
         }
 
         Ok(Expression::FunctionCallExpr(ast::FunctionCallExpression(
-            ast::Identifier(name),
-            arguments
+            Box::from(callee),
+            arguments,
+            self.span_from(start),
         )))
     }
 
+    /// Parses `(params) { ... }` if the parenthesis at `start` opens a function, otherwise nothing is consumed
+    fn function_literal(&mut self, start: usize) -> ParserResult<Option<Expression>> {
+        if self.in_condition {
+            return Ok(None);
+        }
+
+        self.current = start + 1;
+
+        // Collecting parameters fails for anything but a list of names, in which case this is a grouped expression
+        if let Ok(parameters) = self.collect_parameters() {
+            if self.check(TokenType::LeftBrace) {
+                let body = self.function_body()?;
+
+                return Ok(Some(Expression::FunctionDeclareExpr(FunctionDeclareExpression(
+                    None,
+                    parameters,
+                    Box::from(body),
+                    None,
+                    self.span_from(start),
+                ))));
+            }
+        }
+
+        self.current = start;
+        Ok(None)
+    }
+
     fn primary(&mut self) -> ParserResult<Expression> {
+        let start = self.current;
         let token = unwrap_result(self.peek())?.to_owned();
+        let span = token.span();
         let value = token.value;
 
         let result = match token.token_type {
-            TokenType::Null => Expression::LiteralExpr(Literal::Null),
+            TokenType::Null => Expression::LiteralExpr(Literal::Null(ast::NullLiteral(span))),
             TokenType::Integer => {
-                let value = unwrap_result(value)?.get_value().parse::<i32>()?;
-                Expression::LiteralExpr(Literal::Integer(ast::IntegerLiteral(value)))
+                let value = unwrap_result(value)?.get_value().parse::<i64>()?;
+                Expression::LiteralExpr(Literal::Integer(ast::IntegerLiteral(value, span)))
             },
             TokenType::Float => {
-                let value = unwrap_result(value)?.get_value().parse::<f32>()?;
-                Expression::LiteralExpr(Literal::Float(ast::FloatLiteral(value)))
+                let value = unwrap_result(value)?.get_value().parse::<f64>()?;
+                Expression::LiteralExpr(Literal::Float(ast::FloatLiteral(value, span)))
             },
             TokenType::Boolean => {
                 let value = unwrap_result(value)?.get_value().parse::<bool>()?;
-                Expression::LiteralExpr(Literal::Boolean(ast::BooleanLiteral(value)))
+                Expression::LiteralExpr(Literal::Boolean(ast::BooleanLiteral(value, span)))
             },
             TokenType::String => {
                 let value = unwrap_result(value)?.get_value();
-                Expression::LiteralExpr(Literal::String(ast::StringLiteral(value)))
+                Expression::LiteralExpr(Literal::String(ast::StringLiteral(value, span)))
+            },
+            TokenType::Template => {
+                let parts = match unwrap_result(value)? {
+                    TokenLiteral::Template(parts) => parts,
+                    literal => vec![TemplatePart::Text(literal.get_value())],
+                };
+
+                self.template(&parts, span)?
             },
             TokenType::Symbol => {
                 let value = unwrap_result(value)?.get_value();
-                Expression::IdentifierExpr(ast::Identifier(value))
+                Expression::IdentifierExpr(ast::Identifier(value, span))
             }
             TokenType::LeftBracket => {
                 self.advance();
-                return self.parse_bracket();
+                return self.parse_bracket(start);
+            },
+            TokenType::LeftBrace if self.is_map_literal() => {
+                self.advance();
+                return self.parse_map(start);
             },
             TokenType::LeftBrace => {
-                let block = self.block()?;
+                let block = self.braced_block()?;
                 return Ok(Expression::BlockExpr(block));
             },
             TokenType::LeftParen => {
+                if let Some(function) = self.function_literal(start)? {
+                    return Ok(function);
+                }
+
                 self.advance();
-                let expression = self.expression()?;
+                let expression = self.nested_expression()?;
                 self.consume(TokenType::RightParen)?;
-                return Ok(Expression::GroupExpr(Box::from(expression)));
+                return Ok(Expression::GroupExpr(ast::GroupExpression(Box::from(expression), self.span_from(start))));
             },
             _ => error!(ParserError::InvalidToken {
-                expected: vec![TokenType::Integer, TokenType::Float, TokenType::Boolean, TokenType::String, TokenType::Symbol, TokenType::LeftParen],
-                found: token.token_type,
-                pos: token.start,
+                expected: vec![
+                    TokenType::Integer, TokenType::Float, TokenType::Boolean, TokenType::String, TokenType::Template, TokenType::Null,
+                    TokenType::Symbol, TokenType::LeftParen, TokenType::LeftBracket, TokenType::LeftBrace,
+                ],
+                found: token.token_type.to_owned(),
+                span,
             })
         };
         
@@ -579,7 +909,7 @@ impl<'a> Parser<'a> {
         Ok(result)
     }
 
-    fn parse_bracket(&mut self) -> ParserResult<Expression> {
+    fn parse_bracket(&mut self, start: usize) -> ParserResult<Expression> {
         let mut elements: Vec<Expression> = Vec::new();
 
         loop {
@@ -587,7 +917,7 @@ impl<'a> Parser<'a> {
                 break;
             }
 
-            elements.push(self.expression()?);
+            elements.push(self.nested_expression()?);
 
             if !self.matches(TokenType::Comma) {
                 self.consume(TokenType::RightBracket)?;
@@ -595,7 +925,101 @@ impl<'a> Parser<'a> {
             }
         }
 
-        Ok(Expression::LiteralExpr(Literal::List(ast::ListLiteral(elements))))
+        Ok(Expression::LiteralExpr(Literal::List(ast::ListLiteral(elements, self.span_from(start)))))
+    }
+
+    /// Parses the expressions of a string's interpolations, each of which has its own tokens
+    fn template(&mut self, parts: &[TemplatePart], span: Span) -> ParserResult<Expression> {
+        let mut template = Vec::new();
+
+        for part in parts {
+            template.push(match part {
+                TemplatePart::Text(text) => ast::TemplatePart::Text(text.to_owned()),
+                TemplatePart::Tokens(tokens) => {
+                    let mut parser = Parser::from(tokens);
+                    let expression = parser.nested_expression().and_then(|expression| {
+                        parser.consume(TokenType::RightBrace)?;
+                        Ok(expression)
+                    });
+
+                    // Errors recovered from inside the interpolation, e.g. in a block, are the string's errors too
+                    self.errors.append(&mut parser.errors);
+                    ast::TemplatePart::Expression(expression?)
+                },
+            });
+        }
+
+        Ok(Expression::TemplateExpr(ast::TemplateExpression(template, span)))
+    }
+
+    /// Whether the brace at the current token opens a map rather than a block, i.e. `{}` or `{key: ...`
+    fn is_map_literal(&self) -> bool {
+        let token_type = |offset: usize| self.tokens.get(self.current + offset).map(|token| &token.token_type);
+
+        match token_type(1) {
+            Some(TokenType::RightBrace) => true,
+            Some(TokenType::Symbol | TokenType::String | TokenType::Template | TokenType::Integer | TokenType::Boolean | TokenType::Null) =>
+                token_type(2) == Some(&TokenType::Colon),
+            _ => false,
+        }
+    }
+
+    fn parse_map(&mut self, start: usize) -> ParserResult<Expression> {
+        let mut entries: Vec<(Expression, Expression)> = Vec::new();
+
+        loop {
+            if self.matches(TokenType::RightBrace) {
+                break;
+            }
+
+            let key = self.map_key()?;
+            self.consume(TokenType::Colon)?;
+            entries.push((key, self.nested_expression()?));
+
+            if !self.matches(TokenType::Comma) {
+                self.consume(TokenType::RightBrace)?;
+                break;
+            }
+        }
+
+        Ok(Expression::LiteralExpr(Literal::Map(ast::MapLiteral(entries, self.span_from(start)))))
+    }
+
+    /// Parses the key of a map entry, where a bare name stands for a string
+    fn map_key(&mut self) -> ParserResult<Expression> {
+        let token = unwrap_result(self.peek())?.to_owned();
+
+        match token.token_type {
+            TokenType::Symbol => {
+                self.advance();
+                let name = unwrap_result(token.value.to_owned())?.get_value();
+                Ok(Expression::LiteralExpr(Literal::String(ast::StringLiteral(name, token.span()))))
+            },
+            TokenType::String | TokenType::Template | TokenType::Integer | TokenType::Boolean | TokenType::Null => self.primary(),
+            _ => error!(ParserError::InvalidToken {
+                expected: vec![TokenType::Symbol, TokenType::String, TokenType::Template, TokenType::Integer, TokenType::Boolean, TokenType::Null],
+                found: token.token_type.to_owned(),
+                span: token.span(),
+            })
+        }
+    }
+
+    /// Span from the token at `start` to the end of the last consumed token
+    fn span_from(&self, start: usize) -> Span {
+        let first = match self.tokens.get(start) {
+            Some(token) => token,
+            None => return Span::default(),
+        };
+
+        let last = self.tokens[start..self.current.min(self.tokens.len())]
+            .iter()
+            .rev()
+            .find(|token| token.token_type != TokenType::EndOfLine);
+
+        match last {
+            Some(last) => Span::new(first.file, first.start.to_owned(), last.end.to_owned()),
+            None => Span::new(first.file, first.start.to_owned(), first.start.to_owned()),
+        }
     }
 
     fn consume(&mut self, token: TokenType) -> ParserResult<Token> {
@@ -606,24 +1030,11 @@ impl<'a> Parser<'a> {
         let found = unwrap_result(self.peek())?.to_owned();
         error!(ParserError::InvalidToken {
             expected: vec![token],
-            found: found.token_type,
-            pos: found.start,
+            found: found.token_type.to_owned(),
+            span: found.span(),
         })
     }
 
-    fn matches_all_in_order(&mut self, tokens: Vec<TokenType>) -> bool {
-        for (index, token) in tokens.iter().enumerate() {
-            if !self.check(token.to_owned()) {
-                self.current -= index;
-                return false;
-            }
-
-            self.advance();
-        }
-
-        true
-    }
-
     fn match_one_of(&mut self, tokens: Vec<TokenType>) -> bool {
         for token in tokens {
             if self.matches(token) {
@@ -687,12 +1098,117 @@ impl<'a> Parser<'a> {
         self.tokens.get(self.current + 1)
     }
 
-    fn previous(&mut self) -> Option<&Token> {
-        if self.current == 0 {
-            self.warnings.push(ParserError::OutOfBounds { index: String::from("-1") }.into());
-            return None;
-        }
-        
-        self.tokens.get(self.current - 1)
+    fn previous(&self) -> Option<&Token> {
+        self.tokens.get(self.current.checked_sub(1)?)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{errors::{DynamicError, ParserError, ParserErrors, ParserWarning}, lexer::Lexer};
+    use super::{ast::{Node, Program}, ParseOutput, Parser};
+
+    fn parse(source: &str) -> Result<Program, DynamicError> {
+        let mut lexer = Lexer::from(source);
+        let tokens = lexer.tokenize().unwrap_or_else(|err| panic!("Could not lex {:?}: {}", source, err));
+
+        Parser::from(tokens).parse()
+    }
+
+    fn parse_recovering(source: &str) -> ParseOutput {
+        let mut lexer = Lexer::from(source);
+        let tokens = lexer.tokenize().unwrap_or_else(|err| panic!("Could not lex {:?}: {}", source, err));
+
+        Parser::from(tokens).parse_recovering()
+    }
+
+    /// Keywords of the unreachable code warnings of a source
+    fn unreachable_after(source: &str) -> Vec<String> {
+        parse_recovering(source).warnings.iter()
+            .map(|warning| match warning.downcast_ref::<ParserWarning>() {
+                Some(ParserWarning::UnreachableCode { keyword, .. }) => keyword.to_owned(),
+                None => panic!("Not a parser warning: {}", warning),
+            })
+            .collect()
+    }
+
+    /// Every syntax error of a source which fails to parse
+    fn parse_errors(source: &str) -> Vec<ParserError> {
+        let err = match parse(source) {
+            Ok(program) => panic!("Parsed {:?} into {:#?}", source, program),
+            Err(err) => err,
+        };
+
+        let errors = match err.downcast_ref::<ParserErrors>() {
+            Some(ParserErrors(errors)) => errors.iter().collect(),
+            None => vec![&err],
+        };
+
+        errors.into_iter()
+            .map(|err| err.downcast_ref::<ParserError>().unwrap_or_else(|| panic!("Not a parser error: {}", err)).to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn parses_interpolations() {
+        assert!(parse("println(\"a ${x + 1} b ${ { 5; } }\");").is_ok());
+    }
+
+    #[test]
+    fn reports_errors_recovered_from_in_interpolations() {
+        let errors = parse_errors("println(\"a ${ { 1 + ; 5; } } b\");");
+        assert!(matches!(errors.as_slice(), [ParserError::InvalidToken { .. }]), "found {:?}", errors);
+    }
+
+    #[test]
+    fn reports_errors_at_the_end_of_interpolations() {
+        let errors = parse_errors("println(\"${1 2}\");");
+        assert!(matches!(errors.as_slice(), [ParserError::InvalidToken { .. }]), "found {:?}", errors);
+    }
+
+    #[test]
+    fn rejects_loop_control_outside_of_loops() {
+        let errors = parse_errors("break;\nwhile true {\n    f = () {\n        continue;\n    };\n}");
+        let keywords: Vec<&str> = errors.iter()
+            .map(|err| match err {
+                ParserError::OutsideLoop { keyword, .. } => keyword.as_str(),
+                err => panic!("Expected a loop control error, found {:?}", err),
+            })
+            .collect();
+
+        // A function body is outside of the loop it is declared in
+        assert_eq!(keywords, ["break", "continue"]);
+    }
+
+    #[test]
+    fn rejects_loop_control_in_interpolations() {
+        // The interpolation is an expression of its own, so the loop around the string does not count
+        let errors = parse_errors("while true {\n    println(\"${ { break; } }\");\n}");
+        assert!(matches!(errors.as_slice(), [ParserError::OutsideLoop { keyword, .. }] if keyword == "break"), "found {:?}", errors);
+    }
+
+    #[test]
+    fn keeps_the_statements_around_errors() {
+        let output = parse_recovering("a = 1;\nb = * 2;\nc = 3;\nd = (;\n");
+        assert_eq!(output.errors.len(), 2);
+
+        let Node::Program(statements) = output.program else {
+            panic!("Not a program");
+        };
+        assert_eq!(statements.len(), 2);
+    }
+
+    #[test]
+    fn warns_about_unreachable_code() {
+        assert_eq!(unreachable_after("f = () {\n    return 1;\n    println(2);\n};"), ["return"]);
+        assert_eq!(unreachable_after("while true {\n    break;\n\n    x = 1;\n    y = 2;\n}"), ["break"]);
+        assert_eq!(unreachable_after("for i in 0..3 {\n    if i == 1 {\n        continue;\n        i;\n    }\n}"), ["continue"]);
+        assert_eq!(unreachable_after("return 1;\nprintln(2);"), ["return"]);
+    }
+
+    #[test]
+    fn does_not_warn_about_reachable_code() {
+        assert!(unreachable_after("f = (x) {\n    if x {\n        return 1;\n    }\n\n    return 2;\n};").is_empty());
+        assert!(unreachable_after("while true {\n    break;\n\n}").is_empty());
+    }
+}
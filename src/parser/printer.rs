@@ -0,0 +1,398 @@
+
+use crate::{lexer::token::Span, utils::format_float};
+
+use super::ast::{Assignment, BinaryExpression, BlockStatement, Expression, ExpressionStatement, ForInStatement, ForStatement, FunctionCallExpression, FunctionDeclareExpression, IfStatement, IndexAssignment, IndexExpression, Literal, Node, Operator, RangeExpression, ReturnStatement, SliceExpression, TemplateExpression, TemplatePart, UnaryExpression, WhileStatement};
+
+const INDENT: &str = "    ";
+
+/// Prints an AST back as source code in its canonical formatting
+#[derive(Debug, Default)]
+pub struct Printer<'a> {
+    output: String,
+    depth: usize,
+    /// Source the AST was parsed from, literals are printed the way they are spelled in it
+    source: Option<&'a str>,
+    /// Spans of the comments in the source, in order, which are printed before the statement after them
+    comments: &'a [Span],
+}
+
+impl<'a> Printer<'a> {
+    pub fn print(program: &Node) -> String {
+        Printer::default().program(program)
+    }
+
+    /// Prints an AST keeping the comments and the spelling of the literals of the source it was parsed from
+    pub fn format(program: &Node, source: &'a str, comments: &'a [Span]) -> String {
+        Printer { source: Some(source), comments, ..Default::default() }.program(program)
+    }
+
+    fn program(mut self, program: &Node) -> String {
+        match program {
+            Node::Program(nodes) => self.statements(nodes),
+            node => self.statement(node),
+        }
+
+        self.comments_before(usize::MAX);
+        self.output
+    }
+
+    /// Text of a span of the source, if there is one
+    fn source_text(&self, span: &Span) -> Option<&'a str> {
+        self.source.and_then(|source| source.get(span.range()))
+    }
+
+    /// Prints the comments starting before the offset on lines of their own
+    fn comments_before(&mut self, offset: usize) {
+        while let Some((comment, rest)) = self.comments.split_first() {
+            if comment.start.offset >= offset {
+                break;
+            }
+
+            self.comments = rest;
+            if let Some(text) = self.source_text(comment) {
+                self.indent();
+                self.write(text);
+                self.write("\n");
+            }
+        }
+    }
+
+    /// Prints the comment after a statement which starts on the line the statement ends on
+    fn trailing_comment(&mut self, node: &Node, next: Option<&Node>) {
+        let (span, next_start) = (node.span(), next.map_or(usize::MAX, |next| next.span().start.offset));
+
+        if let Some(comment) = self.comments.first().filter(|comment| comment.start.line == span.end.line && comment.start.offset < next_start) {
+            if let Some(text) = self.source_text(comment) {
+                self.write(" ");
+                self.write(text);
+            }
+
+            self.comments = &self.comments[1..];
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.output.push_str(INDENT);
+        }
+    }
+
+    fn statements(&mut self, nodes: &[Node]) {
+        let mut blank_line = false;
+        let mut first = true;
+
+        for (index, node) in nodes.iter().enumerate() {
+            // Empty statements are kept as a single blank line between statements
+            if let Node::EmptyStatement(_) = node {
+                blank_line = !first;
+                continue;
+            }
+
+            if blank_line {
+                self.write("\n");
+                blank_line = false;
+            }
+
+            self.comments_before(node.span().start.offset);
+            self.indent();
+            self.statement(node);
+
+            // A returned function still needs the `;`, only statements ending in a block can leave it out
+            if !self.output.ends_with('}') || matches!(node, Node::ReturnStatement(_)) {
+                self.write(";");
+            }
+
+            let next = nodes[index + 1..].iter().find(|node| !matches!(node, Node::EmptyStatement(_)));
+            self.trailing_comment(node, next);
+            self.write("\n");
+            first = false;
+        }
+    }
+
+    fn block(&mut self, block: &BlockStatement) {
+        let end = block.span().end.offset;
+        let has_comments = self.comments.first().is_some_and(|comment| comment.start.offset < end);
+
+        if !has_comments && block.0.iter().all(|node| matches!(node, Node::EmptyStatement(_))) {
+            self.write("{}");
+            return;
+        }
+
+        self.write("{\n");
+        self.depth += 1;
+        self.statements(&block.0);
+        self.comments_before(end);
+        self.depth -= 1;
+        self.indent();
+        self.write("}");
+    }
+
+    fn statement(&mut self, node: &Node) {
+        match node {
+            Node::Program(nodes) => self.statements(nodes),
+            Node::BlockStatement(block) => self.block(block),
+            Node::ExpressionStatement(ExpressionStatement(expression, _)) => self.expression(expression),
+            Node::EmptyStatement(_) => {},
+            Node::ReturnStatement(ReturnStatement(value, _)) => {
+                self.write("return");
+
+                if let Some(value) = value {
+                    self.write(" ");
+                    self.expression(value);
+                }
+            },
+            Node::ContinueStatement(_) => self.write("continue"),
+            Node::BreakStatement(_) => self.write("break"),
+            Node::IfStatement(statement) => self.if_statement(statement),
+            Node::ElseStatement(statement) => self.block(&statement.0),
+            Node::WhileStatement(WhileStatement(condition, body, _)) => {
+                self.write("while ");
+                self.expression(condition);
+                self.write(" ");
+                self.block(body);
+            },
+            Node::ForStatement(statement) => {
+                let ForStatement(initializer, condition, step, body, _) = statement.as_ref();
+
+                self.write("for ");
+                self.expression(initializer);
+                self.write("; ");
+                self.expression(condition);
+                self.write("; ");
+                self.expression(step);
+                self.write(" ");
+                self.block(body);
+            },
+            Node::ForInStatement(statement) => {
+                let ForInStatement(variables, iterable, body, _) = statement.as_ref();
+                let variables: Vec<&str> = variables.iter().map(|variable| variable.0.as_str()).collect();
+
+                self.write(&format!("for {} in ", variables.join(", ")));
+                self.expression(iterable);
+                self.write(" ");
+                self.block(body);
+            },
+        }
+    }
+
+    fn if_statement(&mut self, statement: &IfStatement) {
+        self.write("if ");
+        self.conditional(statement);
+    }
+
+    /// Prints an if statement without its keyword, so it can be reused for `elif`
+    fn conditional(&mut self, statement: &IfStatement) {
+        let IfStatement(condition, body, otherwise, _) = statement;
+
+        self.expression(condition);
+        self.write(" ");
+        self.block(body);
+
+        match otherwise.as_deref() {
+            Some(Node::IfStatement(elif)) => {
+                self.write(" elif ");
+                self.conditional(elif);
+            },
+            Some(node) => {
+                self.write(" else ");
+                self.statement(node);
+            },
+            None => {},
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::AssignmentExpr(assignment) => self.assignment(assignment),
+            Expression::LiteralExpr(literal) => self.literal(literal),
+            Expression::IdentifierExpr(identifier) => self.write(&identifier.0),
+            Expression::BinaryExpr(BinaryExpression(left, operator, right, _)) => {
+                self.expression(left);
+                self.write(&format!(" {} ", operator));
+                self.expression(right);
+            },
+            Expression::UnaryExpr(UnaryExpression(operator, expression, _)) => {
+                self.write(&operator.to_string());
+                self.expression(expression);
+            },
+            Expression::GroupExpr(group) => {
+                self.write("(");
+                self.expression(&group.0);
+                self.write(")");
+            },
+            Expression::BlockExpr(block) => self.block(block),
+            Expression::FunctionCallExpr(FunctionCallExpression(callee, arguments, _)) => {
+                self.expression(callee);
+                self.write("(");
+                self.expressions(arguments);
+                self.write(")");
+            },
+            Expression::FunctionDeclareExpr(FunctionDeclareExpression(_, parameters, body, _, _)) => {
+                let parameters: Vec<&str> = parameters.iter().map(|parameter| parameter.0.as_str()).collect();
+
+                self.write(&format!("({}) ", parameters.join(", ")));
+                self.block(body);
+            },
+            Expression::IndexExpr(index) => self.index(index),
+            Expression::SliceExpr(SliceExpression(target, from, to, _)) => {
+                self.expression(target);
+                self.write("[");
+                if let Some(from) = from {
+                    self.expression(from);
+                }
+                self.write(":");
+                if let Some(to) = to {
+                    self.expression(to);
+                }
+                self.write("]");
+            },
+            Expression::IndexAssignmentExpr(assignment) => self.index_assignment(assignment),
+            Expression::RangeExpr(RangeExpression(start, end, inclusive, step, _)) => {
+                self.expression(start);
+                self.write(if *inclusive { "..=" } else { ".." });
+                self.expression(end);
+
+                if let Some(step) = step {
+                    self.write(" step ");
+                    self.expression(step);
+                }
+            },
+            // Templates keep their spelling, as the text between interpolations can be quoted and escaped in many ways
+            Expression::TemplateExpr(template) => match self.source_text(template.span()) {
+                Some(text) => self.write(text),
+                None => self.template(template),
+            },
+        }
+    }
+
+    fn template(&mut self, template: &TemplateExpression) {
+        self.write("\"");
+        for part in &template.0 {
+            match part {
+                TemplatePart::Text(text) => self.write(&escape_string(text)),
+                TemplatePart::Expression(expression) => {
+                    self.write("${");
+                    self.expression(expression);
+                    self.write("}");
+                },
+            }
+        }
+        self.write("\"");
+    }
+
+    fn expressions(&mut self, expressions: &[Expression]) {
+        for (index, expression) in expressions.iter().enumerate() {
+            if index > 0 {
+                self.write(", ");
+            }
+
+            self.expression(expression);
+        }
+    }
+
+    fn assignment(&mut self, assignment: &Assignment) {
+        let Assignment(identifier, value, _) = assignment;
+
+        // Compound assignments are desugared by the parser, `x += 1` becomes `x = x + 1`
+        if let Node::ExpressionStatement(ExpressionStatement(Expression::BinaryExpr(BinaryExpression(left, Operator::Arithmetic(operator), right, _)), _)) = value.as_ref() {
+            if let Expression::IdentifierExpr(target) = left.as_ref() {
+                if target.0 == identifier.0 {
+                    self.write(&format!("{} {}= ", identifier.0, operator));
+                    self.expression(right);
+                    return;
+                }
+            }
+        }
+
+        self.write(&format!("{} = ", identifier.0));
+        self.statement(value);
+    }
+
+    fn index(&mut self, index: &IndexExpression) {
+        let IndexExpression(target, index, _) = index;
+
+        self.expression(target);
+        self.write("[");
+        self.expression(index);
+        self.write("]");
+    }
+
+    fn index_assignment(&mut self, assignment: &IndexAssignment) {
+        let IndexAssignment(target, value, _) = assignment;
+        self.index(target);
+
+        // Compound assignments are desugared like they are for variables, `xs[0] += 1` becomes `xs[0] = xs[0] + 1`
+        if let Expression::BinaryExpr(BinaryExpression(left, Operator::Arithmetic(operator), right, _)) = value.as_ref() {
+            if let Expression::IndexExpr(left) = left.as_ref() {
+                if left == target {
+                    self.write(&format!(" {}= ", operator));
+                    self.expression(right);
+                    return;
+                }
+            }
+        }
+
+        self.write(" = ");
+        self.expression(value);
+    }
+
+    fn literal(&mut self, literal: &Literal) {
+        // Numbers and strings keep their spelling, e.g. `0xFF` or a raw string
+        if matches!(literal, Literal::String(_) | Literal::Integer(_) | Literal::Float(_)) {
+            if let Some(text) = self.source_text(literal.span()) {
+                self.write(text);
+                return;
+            }
+        }
+
+        match literal {
+            Literal::String(string) => self.write(&format!("\"{}\"", escape_string(&string.0))),
+            Literal::Float(float) => self.write(&format_float(float.0)),
+            Literal::List(list) => {
+                self.write("[");
+                self.expressions(&list.0);
+                self.write("]");
+            },
+            Literal::Map(map) => {
+                self.write("{");
+                for (index, (key, value)) in map.0.iter().enumerate() {
+                    if index > 0 {
+                        self.write(", ");
+                    }
+
+                    self.expression(key);
+                    self.write(": ");
+                    self.expression(value);
+                }
+                self.write("}");
+            },
+            literal => self.write(&literal.to_string()),
+        }
+    }
+}
+
+fn escape_string(value: &str) -> String {
+    let mut builder = String::with_capacity(value.len());
+
+    let mut chars = value.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '\u{0008}' => builder.push_str("\\b"),
+            '\u{000C}' => builder.push_str("\\f"),
+            '\n' => builder.push_str("\\n"),
+            '\t' => builder.push_str("\\t"),
+            '\r' => builder.push_str("\\r"),
+            '"' => builder.push_str("\\\""),
+            '\\' => builder.push_str("\\\\"),
+            // Would otherwise start an interpolation
+            '$' if chars.peek() == Some(&'{') => builder.push_str("\\$"),
+            _ => builder.push(char),
+        }
+    }
+
+    builder
+}
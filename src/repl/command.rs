@@ -0,0 +1,102 @@
+use std::fs;
+
+use crate::{cli::pretty_path, error, errors::{DynamicError, ReplError}, lexer::Lexer, parser::Parser};
+
+use super::{Session, REPL_SOURCE};
+
+pub const COMMAND_PREFIX: char = ':';
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Tokens(String),
+    Ast(String),
+    Env,
+    Type(String),
+    Load(String),
+    Reset,
+    Help,
+    Quit,
+}
+
+impl Command {
+    pub fn is_command(line: &str) -> bool {
+        line.trim_start().starts_with(COMMAND_PREFIX)
+    }
+
+    pub fn parse(line: &str) -> Result<Self, DynamicError> {
+        let line = line.trim().trim_start_matches(COMMAND_PREFIX);
+        let (name, argument) = match line.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (line, ""),
+        };
+
+        macro_rules! require_argument {
+            ($variant:path) => {
+                if argument.is_empty() {
+                    error!(ReplError::MissingArgument { command: name.to_string() })
+                } else {
+                    $variant(argument.to_string())
+                }
+            };
+        }
+
+        Ok(match name {
+            "tokens" | "t" => require_argument!(Command::Tokens),
+            "ast" | "a" => require_argument!(Command::Ast),
+            "type" => require_argument!(Command::Type),
+            "load" | "l" => require_argument!(Command::Load),
+            "env" | "e" => Command::Env,
+            "reset" => Command::Reset,
+            "help" | "h" | "?" => Command::Help,
+            "quit" | "q" | "exit" => Command::Quit,
+            _ => error!(ReplError::UnknownCommand { name: name.to_string() })
+        })
+    }
+
+    /// Runs the command against the session, returning the text to display
+    pub fn run(self, session: &mut Session) -> Result<Option<String>, DynamicError> {
+        Ok(match self {
+            Command::Tokens(input) => {
+                let file = session.add_source(REPL_SOURCE, &input);
+                let mut lexer = Lexer::with_file(&input, file);
+                Some(Lexer::tokens_to_string(lexer.tokenize()?))
+            },
+            Command::Ast(input) => {
+                let file = session.add_source(REPL_SOURCE, &input);
+                let mut lexer = Lexer::with_file(&input, file);
+                let tokens = lexer.tokenize()?;
+
+                let mut parser = Parser::from(tokens);
+                Some(format!("{:#?}", parser.parse()?))
+            },
+            Command::Env => {
+                session.debug_env()?;
+                None
+            },
+            Command::Type(input) => Some(session.eval(&input)?.get_type().to_string()),
+            Command::Load(path) => {
+                let content = fs::read_to_string(&path)?;
+                Some(session.eval_named(&pretty_path(&path), &content)?.to_string_with_type())
+            },
+            Command::Reset => {
+                session.reset();
+                None
+            },
+            Command::Help => Some(Self::help()),
+            Command::Quit => None,
+        })
+    }
+
+    fn help() -> String {
+        [
+            ":tokens <expr>  Print the tokens produced by the lexer",
+            ":ast <expr>     Print the syntax tree produced by the parser",
+            ":type <expr>    Evaluate an expression and print its type",
+            ":env            Print every name bound in the session",
+            ":load <file>    Evaluate a file in the current session",
+            ":reset          Discard every name bound in the session",
+            ":help           Print this message",
+            ":quit           Exit the REPL",
+        ].join("\n")
+    }
+}
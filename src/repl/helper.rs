@@ -0,0 +1,61 @@
+use rustyline::{completion::{Completer, Pair}, highlight::Highlighter, hint::Hinter, validate::Validator, Context, Helper};
+
+use crate::{evaluator::environment::Environment, lexer::KEYWORDS};
+
+/// Line editor helper completing keywords and names bound in the session
+#[derive(Debug, Default)]
+pub struct ReplHelper {
+    names: Vec<String>,
+}
+
+impl ReplHelper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update_names(&mut self, env: &Environment) {
+        self.names = env.names().into_iter()
+            .map(str::to_string)
+            .collect();
+    }
+
+    fn candidates(&self, prefix: &str) -> Vec<String> {
+        let mut candidates: Vec<String> = KEYWORDS.iter()
+            .map(|(keyword, _)| keyword.to_string())
+            .chain(self.names.iter().cloned())
+            .filter(|candidate| candidate.starts_with(prefix))
+            .collect();
+
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .find(|(_, char)| !(char.is_alphanumeric() || *char == '_'))
+            .map(|(index, char)| index + char.len_utf8())
+            .unwrap_or(0);
+
+        let candidates = self.candidates(&line[start..pos])
+            .into_iter()
+            .map(|candidate| Pair { display: candidate.to_owned(), replacement: candidate })
+            .collect();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+impl Validator for ReplHelper {}
+impl Helper for ReplHelper {}
@@ -0,0 +1,98 @@
+use crate::{errors::LexerError, lexer::{token::TokenType, Lexer}};
+
+/// Shape of the buffered input, used to decide whether more lines are needed
+#[derive(Debug, Default, PartialEq)]
+struct InputState {
+    open_string: bool,
+    open_groups: isize,
+    open_braces: isize,
+    trailing_operator: bool,
+    /// Doc comments which are not followed by anything yet, they belong to the declaration on the next lines
+    trailing_doc: bool,
+}
+
+impl InputState {
+    fn analyze(source: &str) -> Self {
+        let mut state = Self::default();
+        let mut lexer = Lexer::from(source);
+
+        let tokens = match lexer.tokenize() {
+            Ok(tokens) => tokens,
+            Err(err) => {
+                // Any other lexer error is reported once the input is evaluated
+                state.open_string = matches!(err.downcast_ref::<LexerError>(), Some(
+                    LexerError::UnterminatedString { .. }
+                    | LexerError::UnterminatedInterpolation { .. }
+                    | LexerError::UnterminatedComment { .. }
+                ));
+                return state;
+            }
+        };
+
+        for token in tokens {
+            match token.token_type {
+                TokenType::LeftParen | TokenType::LeftBracket => state.open_groups += 1,
+                TokenType::RightParen | TokenType::RightBracket => state.open_groups -= 1,
+                TokenType::LeftBrace => state.open_braces += 1,
+                TokenType::RightBrace => state.open_braces -= 1,
+                _ => {}
+            }
+        }
+
+        // Doc comments at the end of the input are attached to the end of file
+        state.trailing_doc = tokens.last().is_some_and(|token| token.doc.is_some());
+        state.trailing_operator = tokens.iter()
+            .rev()
+            .find(|token| !matches!(token.token_type, TokenType::EndOfLine | TokenType::EndOfFile))
+            .is_some_and(|token| token.token_type.is_operator() || token.token_type == TokenType::Comma);
+
+        state
+    }
+
+    fn is_complete(&self) -> bool {
+        !self.open_string && self.open_groups <= 0 && self.open_braces <= 0 && !self.trailing_operator && !self.trailing_doc
+    }
+
+    /// Whether a statement terminator may be inserted at the end of the current line
+    fn accepts_terminator(&self) -> bool {
+        !self.open_string && self.open_groups <= 0 && !self.trailing_operator && !self.trailing_doc
+    }
+}
+
+/// Collects REPL lines until they form a complete statement
+#[derive(Debug, Default)]
+pub struct InputBuffer {
+    source: String,
+    state: InputState,
+}
+
+impl InputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.source.is_empty()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.state.is_complete()
+    }
+
+    pub fn push_line(&mut self, line: &str) {
+        self.source.push_str(line.trim_end_matches(['\r', '\n']));
+        self.state = InputState::analyze(&self.source);
+
+        // Newlines are not statement separators, so terminate the line unless it obviously continues
+        if self.state.accepts_terminator() && !self.source.ends_with(';') {
+            self.source.push(';');
+        }
+
+        self.source.push('\n');
+    }
+
+    pub fn take(&mut self) -> String {
+        self.state = InputState::default();
+        std::mem::take(&mut self.source)
+    }
+}
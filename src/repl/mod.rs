@@ -0,0 +1,106 @@
+pub mod command;
+pub mod helper;
+pub mod input;
+
+use std::path::PathBuf;
+
+use crate::{diagnostic::SourceMap, error, errors::{DynamicError, EvaluatorError}, evaluator::{environment::Environment, object::Object, yaipl_std, Evaluator}, lexer::{token::{FileId, Span}, Lexer}, parser::{ast::{Node, ProgramTree}, Parser}};
+
+pub const HISTORY_FILE: &str = ".yaipl_history";
+
+/// Name of the sources entered at the prompt, as shown in diagnostics
+pub const REPL_SOURCE: &str = "<repl>";
+
+/// Location of the history file in the user's home directory
+pub fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+pub struct Session {
+    // Declared before `programs` so it is dropped first, as it borrows from them
+    env: Environment<'static>,
+    // Boxed so references into a program stay valid when the vector reallocates
+    #[allow(clippy::vec_box)]
+    programs: Vec<Box<ProgramTree>>,
+    sources: SourceMap,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        let mut env = Environment::new();
+        yaipl_std::initialize(&mut env);
+
+        Self {
+            env,
+            programs: Vec::new(),
+            sources: SourceMap::new(),
+        }
+    }
+
+    pub fn env(&self) -> &Environment<'static> {
+        &self.env
+    }
+
+    /// Every source evaluated in the session, which spans in its errors point into
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    pub fn add_source(&mut self, name: &str, source: &str) -> FileId {
+        self.sources.add(name, source)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Prints the environment using the `yaipl_debug_env` native function
+    pub fn debug_env(&mut self) -> Result<(), DynamicError> {
+        let name = "yaipl_debug_env";
+
+        let function = match self.env.get(name).and_then(|object| object.as_native_function()) {
+            Some(function) => function,
+            None => error!(EvaluatorError::ObjectNotFound { name: name.to_owned(), span: Span::default() })
+        };
+
+        (function.2)(&mut self.env, Vec::new());
+        Ok(())
+    }
+
+    pub fn eval(&mut self, input: &str) -> Result<Object, DynamicError> {
+        self.eval_named(REPL_SOURCE, input)
+    }
+
+    /// Evaluates a source, naming it in diagnostics
+    pub fn eval_named(&mut self, name: &str, input: &str) -> Result<Object, DynamicError> {
+        let file = self.add_source(name, input);
+        let mut lexer = Lexer::with_file(input, file);
+        let tokens = lexer.tokenize()?;
+
+        let mut parser = Parser::from(tokens);
+        let ast = match parser.parse()? {
+            Node::Program(ast) => ast,
+            _ => error!("AST is not a program node.")
+        };
+
+        self.programs.push(Box::new(ast));
+
+        // SAFETY: The program is boxed and owned by the session, so it lives at least as long as
+        // the environment which may hold names and function objects pointing into it.
+        let ast: &'static ProgramTree = unsafe { &*(self.programs.last().expect("Program was just pushed").as_ref() as *const ProgramTree) };
+
+        let mut evaluator = Evaluator::with_env(ast, std::mem::take(&mut self.env));
+        let result = evaluator.eval();
+        self.env = evaluator.into_env();
+
+        result
+    }
+}
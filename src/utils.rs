@@ -7,6 +7,17 @@ pub fn unwrap_result<T>(element: Option<T>) -> Result<T, DynamicError> {
     }
 }
 
+/// Formats a float so that it reads back as the same float, with a '.' or an exponent to tell it apart from an integer
+pub fn format_float(value: f64) -> String {
+    if value.is_nan() {
+        String::from("nan")
+    } else if value.is_infinite() {
+        String::from(if value > 0.0 { "inf" } else { "-inf" })
+    } else {
+        format!("{:?}", value)
+    }
+}
+
 #[cfg(not(target_os = "windows"))]
 pub mod colors {
     pub const RED: &str = "\x1b[31m";